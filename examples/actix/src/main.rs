@@ -1,21 +1,14 @@
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-};
-
-use actix_web::{
-    App, HttpResponse, HttpServer, Responder, get,
-    web::{self, Query},
-};
+use actix_web::{App, HttpResponse, HttpServer, Responder, get, route, web};
 use socio::{
     Socio,
-    integrations::Callback,
-    oauth2::{AuthorizationCode, EmptyExtraTokenFields, PkceCodeVerifier},
+    flow::{FlowStateStore, MemoryFlowStateStore},
+    integrations::actix::ExtractCallback,
+    oauth2::EmptyExtraTokenFields,
 };
 
 #[derive(Clone, Debug, Default)]
 pub struct AppState {
-    requests: Arc<Mutex<HashMap<String, String>>>,
+    requests: MemoryFlowStateStore,
 }
 
 #[actix_web::main]
@@ -33,40 +26,21 @@ async fn main() -> std::io::Result<()> {
 
 #[get("/redirect")]
 async fn redirect(data: web::Data<AppState>) -> impl Responder {
-    let authorization_request = socio().authorize().unwrap();
-
-    let redirect = authorization_request.redirect_actix();
-
-    {
-        let mut requests = data.requests.lock().expect("lock poisoned");
-        requests.insert(
-            authorization_request.csrf_token.into_secret(),
-            authorization_request.pkce_verifier.into_secret(),
-        );
-    }
+    let authorization_request = socio().begin_login(&data.requests).await.unwrap();
 
-    redirect
+    authorization_request.redirect_actix()
 }
 
-#[get("/callback")]
-async fn callback(query: Query<Callback>, data: web::Data<AppState>) -> impl Responder {
-    let Query(Callback { code, state }) = query;
-
-    let pkce_verifier = {
-        let requests = data.requests.lock().expect("lock poisoned");
-
-        let pkce_verifier = requests
-            .get(&state)
-            .expect("No matching CSRF Token found")
-            .to_string();
-
-        PkceCodeVerifier::new(pkce_verifier)
-    };
-
-    let code = AuthorizationCode::new(code);
+#[route("/callback", method = "GET", method = "POST")]
+async fn callback(callback: ExtractCallback, data: web::Data<AppState>) -> impl Responder {
+    let (code, flow_state) = data
+        .requests
+        .verify(callback.0)
+        .await
+        .expect("No matching CSRF Token found");
 
     socio()
-        .exchange_code::<EmptyExtraTokenFields>(code, pkce_verifier)
+        .exchange_code::<EmptyExtraTokenFields>(code, flow_state.pkce_verifier)
         .await
         .unwrap();
 
@@ -1,22 +1,14 @@
-use axum::{
-    Router,
-    extract::{Query, State},
-    http::StatusCode,
-    routing::get,
-};
+use axum::{Router, extract::State, http::StatusCode, routing::get};
 use socio::{
     Socio,
-    integrations::{Callback, axum::Redirect},
-    oauth2::{AuthorizationCode, EmptyExtraTokenFields, PkceCodeVerifier},
-};
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
+    flow::{FlowStateStore, MemoryFlowStateStore},
+    integrations::axum::{ExtractCallback, Redirect},
+    oauth2::EmptyExtraTokenFields,
 };
 
 #[derive(Clone, Debug, Default)]
 pub struct AppState {
-    requests: Arc<Mutex<HashMap<String, String>>>,
+    requests: MemoryFlowStateStore,
 }
 
 #[tokio::main]
@@ -25,7 +17,7 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let app = Router::new()
         .route("/redirect", get(redirect))
-        .route("/callback", get(callback))
+        .route("/callback", get(callback).post(callback))
         .with_state(state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
@@ -35,38 +27,24 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 pub async fn redirect(State(state): State<AppState>) -> Redirect {
-    let authorization_request = socio().authorize().unwrap();
-
-    let redirect = authorization_request.redirect_axum().unwrap();
-
-    {
-        let mut requests = state.requests.lock().expect("lock poisoned");
-        requests.insert(
-            authorization_request.csrf_token.into_secret(),
-            authorization_request.pkce_verifier.into_secret(),
-        );
-    }
+    let authorization_request = socio().begin_login(&state.requests).await.unwrap();
 
-    redirect
+    authorization_request.redirect_axum().unwrap()
 }
 
 #[axum::debug_handler]
-pub async fn callback(Query(query): Query<Callback>, State(state): State<AppState>) -> StatusCode {
-    let pkce_verifier = {
-        let requests = state.requests.lock().expect("lock poisoned");
-
-        let pkce_verifier = requests
-            .get(&query.state)
-            .expect("No matching CSRF Token found")
-            .to_string();
-
-        PkceCodeVerifier::new(pkce_verifier)
-    };
-
-    let code = AuthorizationCode::new(query.code);
+pub async fn callback(
+    State(state): State<AppState>,
+    ExtractCallback(callback): ExtractCallback,
+) -> StatusCode {
+    let (code, flow_state) = state
+        .requests
+        .verify(callback)
+        .await
+        .expect("No matching CSRF Token found");
 
     socio()
-        .exchange_code::<EmptyExtraTokenFields>(code, pkce_verifier)
+        .exchange_code::<EmptyExtraTokenFields>(code, flow_state.pkce_verifier)
         .await
         .unwrap();
 
@@ -5,18 +5,14 @@ use axum::{
 };
 use socio::{
     Socio,
+    flow::MemoryFlowStateStore,
     integrations::{Callback, axum::Redirect},
-    oauth2::{AuthorizationCode, PkceCodeVerifier},
 };
 use socio_providers::google::{Google, GoogleUser};
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-};
 
 #[derive(Clone, Debug, Default)]
 pub struct AppState {
-    requests: Arc<Mutex<HashMap<String, String>>>,
+    requests: MemoryFlowStateStore,
 }
 
 #[tokio::main]
@@ -35,19 +31,9 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 pub async fn redirect(State(state): State<AppState>) -> Redirect {
-    let authorization_request = socio().authorize().unwrap();
-
-    let redirect = authorization_request.redirect_axum().unwrap();
+    let authorization_request = socio().begin_login(&state.requests).await.unwrap();
 
-    {
-        let mut requests = state.requests.lock().expect("lock poisoned");
-        requests.insert(
-            authorization_request.csrf_token.into_secret(),
-            authorization_request.pkce_verifier.into_secret(),
-        );
-    }
-
-    redirect
+    authorization_request.redirect_axum().unwrap()
 }
 
 #[axum::debug_handler]
@@ -55,21 +41,8 @@ pub async fn callback(
     Query(query): Query<Callback>,
     State(state): State<AppState>,
 ) -> Json<GoogleUser> {
-    let pkce_verifier = {
-        let requests = state.requests.lock().expect("lock poisoned");
-
-        let pkce_verifier = requests
-            .get(&query.state)
-            .expect("No matching CSRF Token found")
-            .to_string();
-
-        PkceCodeVerifier::new(pkce_verifier)
-    };
-
-    let code = AuthorizationCode::new(query.code);
-
     let token = socio()
-        .exchange_code_for_user(code, pkce_verifier)
+        .complete_login_for_user(&state.requests, query, None)
         .await
         .unwrap();
 
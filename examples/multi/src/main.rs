@@ -6,19 +6,15 @@ use axum::{
 };
 use socio::{
     Socio,
+    flow::{FlowStateStore, MemoryFlowStateStore},
     integrations::{Callback, axum::Redirect},
-    oauth2::{AuthorizationCode, PkceCodeVerifier},
     providers::{Dynamic, StandardUser},
 };
 use socio_providers::{google::Google, microsoft::Microsoft};
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-};
 
 #[derive(Clone, Debug, Default)]
 pub struct AppState {
-    requests: Arc<Mutex<HashMap<String, (String, String)>>>,
+    requests: MemoryFlowStateStore,
 }
 
 #[tokio::main]
@@ -58,20 +54,11 @@ pub async fn home() -> Html<&'static str> {
 
 pub async fn redirect(State(state): State<AppState>, Path(key): Path<String>) -> Redirect {
     let authorization_request = socio(key.as_str())
-        .authorize()
+        .begin_login_for_provider(&state.requests, &key)
+        .await
         .expect("Failed to authorize");
 
-    let redirect = authorization_request.redirect_axum().unwrap();
-
-    {
-        let mut requests = state.requests.lock().expect("lock poisoned");
-        requests.insert(
-            authorization_request.csrf_token.into_secret(),
-            (key, authorization_request.pkce_verifier.into_secret()),
-        );
-    }
-
-    redirect
+    authorization_request.redirect_axum().unwrap()
 }
 
 #[axum::debug_handler]
@@ -79,20 +66,18 @@ pub async fn callback(
     Query(query): Query<Callback>,
     State(state): State<AppState>,
 ) -> Json<StandardUser> {
-    let (key, pkce_verifier) = {
-        let requests = state.requests.lock().expect("lock poisoned");
-
-        let (key, pkce_verifier) = requests
-            .get(&query.state)
-            .expect("No matching CSRF Token found");
-
-        (key.clone(), PkceCodeVerifier::new(pkce_verifier.clone()))
-    };
+    let (code, flow_state) = state
+        .requests
+        .verify(query)
+        .await
+        .expect("No matching CSRF Token found");
 
-    let code = AuthorizationCode::new(query.code);
+    let key = flow_state
+        .provider
+        .expect("Login was started without a provider");
 
     let response = socio(key.as_str())
-        .exchange_code_standard(code, pkce_verifier)
+        .exchange_code_standard(code, flow_state.pkce_verifier, flow_state.nonce.as_ref())
         .await
         .expect("Failed to exchange code");
 
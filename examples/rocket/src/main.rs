@@ -1,55 +1,51 @@
 #![allow(dead_code)]
 
-use std::{collections::HashMap, sync::Mutex};
-
-use rocket::{State, get, http::Status, launch, routes};
+use rocket::{State, get, http::Status, launch, post, routes};
 use socio::{
     Socio,
-    integrations::rocket::Redirect,
-    oauth2::{AuthorizationCode, EmptyExtraTokenFields, PkceCodeVerifier},
+    flow::{FlowStateStore, MemoryFlowStateStore},
+    integrations::rocket::{ExtractCallback, Redirect},
+    oauth2::EmptyExtraTokenFields,
 };
 
 #[launch]
 fn rocket() -> _ {
-    let requests = Mutex::new(HashMap::<String, String>::new());
+    let requests = MemoryFlowStateStore::default();
 
     rocket::build()
         .configure(rocket::Config::figment().merge(("port", 3000)))
-        .mount("/", routes![redirect, callback])
+        .mount("/", routes![redirect, callback, form_post_callback])
         .manage(requests)
 }
 
 #[get("/redirect")]
-async fn redirect(requests: &State<Mutex<HashMap<String, String>>>) -> Redirect {
-    let authorization_request = socio().authorize().unwrap();
-
-    {
-        let mut requests = requests.lock().expect("lock poisoned");
-        requests.insert(
-            authorization_request.csrf_token.into_secret(),
-            authorization_request.pkce_verifier.into_secret(),
-        );
-    }
+async fn redirect(requests: &State<MemoryFlowStateStore>) -> Redirect {
+    let authorization_request = socio().begin_login(requests.inner()).await.unwrap();
 
     Redirect::new(authorization_request.url)
 }
 
-#[get("/callback?<code>&<state>")]
-async fn callback(
-    code: String,
-    state: String,
-    requests: &State<Mutex<HashMap<String, String>>>,
+#[get("/callback")]
+async fn callback(callback: ExtractCallback, requests: &State<MemoryFlowStateStore>) -> Status {
+    complete_login(callback, requests).await
+}
+
+#[post("/callback", data = "<callback>")]
+async fn form_post_callback(
+    callback: ExtractCallback,
+    requests: &State<MemoryFlowStateStore>,
 ) -> Status {
-    let pkce_verifier = {
-        let requests = requests.lock().expect("lock poisoned");
-        let pkce_verifier = requests.get(&state).expect("state not found").clone();
-        PkceCodeVerifier::new(pkce_verifier)
-    };
+    complete_login(callback, requests).await
+}
 
-    let code = AuthorizationCode::new(code);
+async fn complete_login(
+    ExtractCallback(callback): ExtractCallback,
+    requests: &State<MemoryFlowStateStore>,
+) -> Status {
+    let (code, flow_state) = requests.verify(callback).await.expect("state not found");
 
     socio()
-        .exchange_code::<EmptyExtraTokenFields>(code, pkce_verifier)
+        .exchange_code::<EmptyExtraTokenFields>(code, flow_state.pkce_verifier)
         .await
         .unwrap();
 
@@ -1,5 +1,5 @@
 use socio::{
-    oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, Scope, TokenUrl},
+    oauth2::{AuthType, AuthUrl, ClientId, ClientSecret, RedirectUrl, RevocationUrl, TokenUrl},
     types::SocioClient,
 };
 
@@ -8,39 +8,51 @@ pub fn read_config(key: &str) -> SocioClient {
     let config = serde_json::from_str::<serde_json::Value>(&config_content).unwrap();
     let config = &config[key];
 
-    let config = SocioClient {
-        client_id: ClientId::new(get_config_string(&config, "client_id")),
-        client_secret: ClientSecret::new(get_config_string(&config, "client_secret")),
-        authorize_endpoint: AuthUrl::new(get_config_string(&config, "authorize_endpoint")).unwrap(),
-        token_endpoint: TokenUrl::new(get_config_string(&config, "token_endpoint"))
-            .expect("Invalid token endpoint"),
-        scopes: get_config_scopes(&config, "scopes"),
-        redirect_uri: RedirectUrl::new(get_config_string(&config, "redirect_uri"))
-            .expect("Invalid redirect URI"),
-    };
+    let mut client = SocioClient::new(
+        ClientId::new(get_config_string(config, "client_id")),
+        ClientSecret::new(get_config_string(config, "client_secret")),
+        RedirectUrl::new(get_config_string(config, "redirect_uri")).expect("Invalid redirect URI"),
+        AuthUrl::new(get_config_string(config, "authorize_endpoint")).unwrap(),
+        TokenUrl::new(get_config_string(config, "token_endpoint")).expect("Invalid token endpoint"),
+    )
+    .with_scopes(get_config_string_list(config, "scopes"))
+    .with_iss_parameter_supported(config["iss_parameter_supported"].as_bool().unwrap_or(false));
 
-    config
+    if let Some(issuer) = config["issuer"].as_str() {
+        client = client.with_issuer(issuer);
+    }
+    if let Some(url) = config["revocation_endpoint"].as_str() {
+        client = client.with_revocation_endpoint(
+            RevocationUrl::new(url.to_string()).expect("Invalid revocation endpoint"),
+        );
+    }
+    if let Some(url) = config["userinfo_endpoint"].as_str() {
+        client = client.with_userinfo_endpoint(url.parse().expect("Invalid userinfo endpoint"));
+    }
+    if let Some(mode) = config["response_mode"].as_str() {
+        client = client.with_response_mode(
+            serde_json::from_value(mode.into()).expect("Invalid response mode"),
+        );
+    }
+    if config["auth_type"].as_str() == Some("request_body") {
+        client = client.with_auth_type(AuthType::RequestBody);
+    }
+
+    client
 }
 
 fn get_config_string(config: &serde_json::Value, key: &str) -> String {
     config[key]
         .as_str()
-        .expect(&format!("The key '{key}' is missing or not a string"))
+        .unwrap_or_else(|| panic!("The key '{key}' is missing or not a string"))
         .to_string()
 }
 
 fn get_config_string_list(config: &serde_json::Value, key: &str) -> Vec<String> {
     config[key]
         .as_array()
-        .expect(&format!("The key '{key}' is missing or not a list"))
+        .unwrap_or_else(|| panic!("The key '{key}' is missing or not a list"))
         .iter()
         .map(|v| v.as_str().unwrap().to_string())
         .collect()
 }
-
-fn get_config_scopes(config: &serde_json::Value, key: &str) -> Vec<Scope> {
-    get_config_string_list(config, key)
-        .into_iter()
-        .map(|s| Scope::new(s))
-        .collect()
-}
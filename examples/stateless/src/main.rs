@@ -0,0 +1,82 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::{HeaderMap, header},
+    response::IntoResponse,
+    routing::get,
+};
+use socio::{
+    Socio,
+    integrations::Callback,
+    sealed::{SealingKey, StateCookie, StateSealer},
+};
+use socio_providers::google::Google;
+
+#[derive(Clone, Debug)]
+pub struct AppState {
+    sealer: StateSealer,
+    cookie: StateCookie,
+}
+
+#[tokio::main]
+pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Every replica must share the same keys. Add the previous key with
+    // `with_decryption_key` while rotating.
+    let key = match std::env::var("SOCIO_SEALING_KEY") {
+        Ok(key) => SealingKey::from_base64(&key)?,
+        Err(_) => SealingKey::generate(),
+    };
+
+    let state = AppState {
+        sealer: StateSealer::new(key),
+        cookie: StateCookie::for_client(socio().client()),
+    };
+
+    let app = Router::new()
+        .route("/redirect", get(redirect))
+        .route("/callback", get(callback))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+pub async fn redirect(State(state): State<AppState>) -> impl IntoResponse {
+    let (authorization_request, sealed) = socio().begin_sealed_login(&state.sealer).unwrap();
+
+    let set_cookie = state.cookie.set_cookie(&sealed, state.sealer.max_age());
+
+    (
+        [(header::SET_COOKIE, set_cookie)],
+        authorization_request.redirect_axum().unwrap(),
+    )
+}
+
+#[axum::debug_handler]
+pub async fn callback(
+    Query(query): Query<Callback>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let sealed = headers
+        .get(header::COOKIE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| state.cookie.find(value))
+        .expect("Missing state cookie");
+
+    let response = socio()
+        .complete_sealed_login(&state.sealer, sealed, query, None)
+        .await
+        .unwrap();
+
+    (
+        [(header::SET_COOKIE, state.cookie.remove_cookie())],
+        Json(response.user),
+    )
+}
+
+fn socio() -> Socio<Google> {
+    Socio::new(shared::read_config("google"), Google)
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use oauth2::{AuthType, AuthUrl, RevocationUrl, Scope, TokenUrl};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    error,
+    http_client::{SharedHttpClient, parse_json},
+};
+
+/// How long discovered metadata is reused before it is fetched again.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// OpenID Provider metadata as published at `/.well-known/openid-configuration`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: AuthUrl,
+    pub token_endpoint: TokenUrl,
+    #[serde(default)]
+    pub userinfo_endpoint: Option<Url>,
+    pub jwks_uri: Url,
+    #[serde(default)]
+    pub revocation_endpoint: Option<RevocationUrl>,
+    #[serde(default)]
+    pub end_session_endpoint: Option<Url>,
+    #[serde(default)]
+    pub scopes_supported: Option<Vec<String>>,
+    #[serde(default)]
+    pub response_modes_supported: Option<Vec<String>>,
+    #[serde(default)]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    #[serde(default)]
+    pub token_endpoint_auth_methods_supported: Option<Vec<String>>,
+    /// Whether the provider sends the `iss` parameter with authorization responses.
+    #[serde(default)]
+    pub authorization_response_iss_parameter_supported: bool,
+}
+
+impl ProviderMetadata {
+    /// Returns the metadata of `issuer`, reusing a previously discovered copy when it is
+    /// recent enough.
+    pub async fn discover(
+        issuer: &str,
+        http_client: &SharedHttpClient,
+    ) -> error::Result<Arc<ProviderMetadata>> {
+        if let Some(metadata) = cached(issuer) {
+            return Ok(metadata);
+        }
+
+        let metadata = Arc::new(Self::fetch(issuer, http_client).await?);
+
+        cache()
+            .lock()
+            .expect("lock poisoned")
+            .insert(issuer.to_string(), (Instant::now(), metadata.clone()));
+
+        Ok(metadata)
+    }
+
+    /// Fetches the metadata of `issuer`, bypassing the cache.
+    ///
+    /// Fails unless the published `issuer` is identical to `issuer`, trailing slash included,
+    /// as OpenID Connect Discovery 1.0 §4.3 requires.
+    pub async fn fetch(
+        issuer: &str,
+        http_client: &SharedHttpClient,
+    ) -> error::Result<ProviderMetadata> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            issuer.trim_end_matches('/')
+        );
+
+        let response = http_client.get(&url, None).await?;
+        let metadata = parse_json::<ProviderMetadata>(&response)?;
+
+        if metadata.issuer != issuer {
+            return Err(error::Error::IssuerMismatch {
+                expected: issuer.to_string(),
+                found: metadata.issuer,
+            });
+        }
+
+        Ok(metadata)
+    }
+
+    /// Returns the `openid`, `profile` and `email` scopes, leaving out the ones the provider
+    /// says it does not support.
+    pub fn default_scopes(&self) -> Vec<Scope> {
+        ["openid", "profile", "email"]
+            .iter()
+            .filter(|scope| **scope == "openid" || self.supports_scope(scope))
+            .map(|scope| Scope::new(scope.to_string()))
+            .collect()
+    }
+
+    pub fn supports_scope(&self, scope: &str) -> bool {
+        self.scopes_supported
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|s| s == scope))
+    }
+
+    /// Returns how to send the client credentials to the token endpoint, preferring HTTP
+    /// basic authentication which is the default when the provider does not say.
+    pub fn auth_type(&self) -> AuthType {
+        match &self.token_endpoint_auth_methods_supported {
+            Some(methods)
+                if !methods.iter().any(|m| m == "client_secret_basic")
+                    && methods.iter().any(|m| m == "client_secret_post") =>
+            {
+                AuthType::RequestBody
+            }
+            _ => AuthType::BasicAuth,
+        }
+    }
+
+    /// Returns the supported ID token signing algorithms this crate can verify.
+    #[cfg(feature = "jwt")]
+    pub fn signing_algorithms(&self) -> Vec<jsonwebtoken::Algorithm> {
+        self.id_token_signing_alg_values_supported
+            .iter()
+            .filter_map(|alg| alg.parse().ok())
+            .collect()
+    }
+}
+
+type MetadataCache = Mutex<HashMap<String, (Instant, Arc<ProviderMetadata>)>>;
+
+fn cache() -> &'static MetadataCache {
+    static CACHE: OnceLock<MetadataCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn cached(issuer: &str) -> Option<Arc<ProviderMetadata>> {
+    let cache = cache().lock().expect("lock poisoned");
+    cache
+        .get(issuer)
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < CACHE_TTL)
+        .map(|(_, metadata)| metadata.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_metadata() {
+        let metadata = serde_json::from_str::<ProviderMetadata>(
+            r#"{
+                "issuer": "https://accounts.google.com",
+                "authorization_endpoint": "https://accounts.google.com/o/oauth2/v2/auth",
+                "token_endpoint": "https://oauth2.googleapis.com/token",
+                "userinfo_endpoint": "https://openidconnect.googleapis.com/v1/userinfo",
+                "revocation_endpoint": "https://oauth2.googleapis.com/revoke",
+                "jwks_uri": "https://www.googleapis.com/oauth2/v3/certs",
+                "scopes_supported": ["openid", "email"],
+                "id_token_signing_alg_values_supported": ["RS256", "none"]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(metadata.end_session_endpoint.is_none());
+        assert!(matches!(metadata.auth_type(), AuthType::BasicAuth));
+        assert_eq!(
+            metadata.default_scopes(),
+            vec![Scope::new("openid".into()), Scope::new("email".into())]
+        );
+        #[cfg(feature = "jwt")]
+        assert_eq!(
+            metadata.signing_algorithms(),
+            vec![jsonwebtoken::Algorithm::RS256]
+        );
+    }
+}
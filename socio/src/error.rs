@@ -1,29 +1,90 @@
+use crate::{http_client::HttpError, integrations::AuthorizationError};
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
-    ReqwestError(#[from] reqwest::Error),
+    HttpError(#[from] HttpError),
+
+    #[error("Unexpected HTTP status {0}")]
+    UnexpectedStatus(http::StatusCode),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
 
     #[error(transparent)]
     ExchangingCodeError(
         #[from]
         oauth2::RequestTokenError<
-            oauth2::HttpClientError<reqwest::Error>,
+            HttpError,
             oauth2::StandardErrorResponse<oauth2::basic::BasicErrorResponseType>,
         >,
     ),
 
+    #[error(transparent)]
+    RevocationError(
+        #[from]
+        oauth2::RequestTokenError<
+            HttpError,
+            oauth2::StandardErrorResponse<oauth2::RevocationErrorResponseType>,
+        >,
+    ),
+
+    #[error(transparent)]
+    ConfigurationError(#[from] oauth2::ConfigurationError),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+
     #[error(transparent)]
     HeaderValueError(#[from] http::header::InvalidHeaderValue),
 
     #[error("CSRF token mismatch")]
     CsrfTokenMismatch,
 
+    #[error("Authorization failed: {0}")]
+    AuthorizationError(AuthorizationError),
+
+    #[error("Callback has neither an authorization code nor an error")]
+    MissingAuthorizationCode,
+
+    #[error("No pending login found for the given state")]
+    FlowStateNotFound,
+
+    #[error("Login state has expired")]
+    FlowStateExpired,
+
+    #[error("Login state was started for another provider")]
+    FlowStateProviderMismatch,
+
+    #[error("Sealed login state could not be unsealed")]
+    InvalidSealedState,
+
+    #[error("Issuer mismatch, expected '{expected}' but found '{found}'")]
+    IssuerMismatch { expected: String, found: String },
+
+    #[error("Callback is missing the iss parameter")]
+    MissingIssuer,
+
+    #[error("Callback could not be verified: {0}")]
+    InvalidCallback(String),
+
     #[cfg(feature = "jwt")]
     #[error(transparent)]
     JwtError(#[from] jsonwebtoken::errors::Error),
 
+    #[cfg(feature = "jwt")]
+    #[error("Invalid ID token: {0}")]
+    InvalidIdToken(String),
+
+    #[cfg(feature = "saml")]
+    #[error("Invalid SAML response: {0}")]
+    InvalidSamlResponse(String),
+
+    #[error("UserInfo subject does not match the ID token subject")]
+    UserInfoSubjectMismatch,
+
     #[error(transparent)]
     Custom(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
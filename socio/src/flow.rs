@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use oauth2::{AuthorizationCode, CsrfToken, PkceCodeVerifier};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error,
+    integrations::Callback,
+    types::{AuthorizationRequest, Nonce},
+};
+
+/// The state that has to survive between redirecting the user to the provider and the
+/// provider redirecting the user back to the callback.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FlowState {
+    pub csrf_token: CsrfToken,
+    pub pkce_verifier: PkceCodeVerifier,
+    #[serde(default)]
+    pub nonce: Option<Nonce>,
+    /// Optional key identifying the provider that started the flow, for setups with more
+    /// than one provider sharing a callback.
+    pub provider: Option<String>,
+    /// Issuer expected in the `iss` parameter of the callback.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Whether the callback must carry the `iss` parameter.
+    #[serde(default)]
+    pub iss_required: bool,
+    pub issued_at: SystemTime,
+}
+
+impl FlowState {
+    pub fn new(csrf_token: CsrfToken, pkce_verifier: PkceCodeVerifier) -> Self {
+        FlowState {
+            csrf_token,
+            pkce_verifier,
+            nonce: None,
+            provider: None,
+            issuer: None,
+            iss_required: false,
+            issued_at: SystemTime::now(),
+        }
+    }
+
+    pub fn with_nonce(mut self, nonce: Option<Nonce>) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    pub fn with_issuer(mut self, issuer: Option<String>, iss_required: bool) -> Self {
+        self.issuer = issuer;
+        self.iss_required = iss_required;
+        self
+    }
+
+    /// Copies the state out of an authorization request, leaving the request usable for
+    /// redirecting the user.
+    pub fn from_authorization_request(request: &AuthorizationRequest) -> Self {
+        FlowState::new(
+            request.csrf_token.clone(),
+            PkceCodeVerifier::new(request.pkce_verifier.secret().clone()),
+        )
+        .with_nonce(request.nonce.clone())
+        .with_issuer(request.issuer.clone(), request.iss_required)
+    }
+
+    /// Checks that the flow was started for `provider`, `None` meaning a flow started
+    /// without one.
+    pub fn verify_provider(&self, provider: Option<&str>) -> error::Result<()> {
+        if self.provider.as_deref() != provider {
+            return Err(error::Error::FlowStateProviderMismatch);
+        }
+        Ok(())
+    }
+
+    pub fn is_expired(&self, ttl: Duration) -> bool {
+        self.issued_at
+            .elapsed()
+            .map(|elapsed| elapsed > ttl)
+            .unwrap_or(false)
+    }
+}
+
+/// Persists [`FlowState`] keyed by the `state` parameter sent to the provider.
+///
+/// Implementations must guarantee that a state can be taken at most once.
+#[async_trait]
+pub trait FlowStateStore: Send + Sync {
+    async fn save(&self, state: FlowState) -> error::Result<()>;
+
+    /// Removes and returns the state stored for the given `state` parameter, if any.
+    async fn take(&self, state: &str) -> error::Result<Option<FlowState>>;
+
+    /// Consumes the state matching the callback and verifies the callback against it.
+    async fn verify(&self, callback: Callback) -> error::Result<(AuthorizationCode, FlowState)> {
+        let state = self
+            .take(&callback.state)
+            .await?
+            .ok_or(error::Error::FlowStateNotFound)?;
+
+        let code = callback.verify(&state)?;
+
+        Ok((code, state))
+    }
+}
+
+#[async_trait]
+impl<T: FlowStateStore + ?Sized> FlowStateStore for Arc<T> {
+    async fn save(&self, state: FlowState) -> error::Result<()> {
+        self.as_ref().save(state).await
+    }
+
+    async fn take(&self, state: &str) -> error::Result<Option<FlowState>> {
+        self.as_ref().take(state).await
+    }
+}
+
+/// An in-process [`FlowStateStore`] that expires entries after a fixed time to live.
+///
+/// Taking a state past its time to live removes it and fails with
+/// [`error::Error::FlowStateExpired`].
+///
+/// Only suitable when every callback is served by the same process that started the flow.
+#[derive(Clone, Debug)]
+pub struct MemoryFlowStateStore {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, FlowState>>>,
+}
+
+impl Default for MemoryFlowStateStore {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10 * 60))
+    }
+}
+
+impl MemoryFlowStateStore {
+    pub fn new(ttl: Duration) -> Self {
+        MemoryFlowStateStore {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Drops every expired entry.
+    pub fn purge_expired(&self) {
+        let mut entries = self.entries.lock().expect("lock poisoned");
+        entries.retain(|_, state| !state.is_expired(self.ttl));
+    }
+}
+
+#[async_trait]
+impl FlowStateStore for MemoryFlowStateStore {
+    async fn save(&self, state: FlowState) -> error::Result<()> {
+        let mut entries = self.entries.lock().expect("lock poisoned");
+        entries.retain(|_, state| !state.is_expired(self.ttl));
+        entries.insert(state.csrf_token.secret().clone(), state);
+        Ok(())
+    }
+
+    async fn take(&self, state: &str) -> error::Result<Option<FlowState>> {
+        let mut entries = self.entries.lock().expect("lock poisoned");
+        match entries.remove(state) {
+            Some(state) if state.is_expired(self.ttl) => Err(error::Error::FlowStateExpired),
+            state => Ok(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow_state(secret: &str) -> FlowState {
+        FlowState::new(
+            CsrfToken::new(secret.to_string()),
+            PkceCodeVerifier::new("verifier".to_string()),
+        )
+    }
+
+    fn callback(state: &str) -> Callback {
+        Callback::new("code", state)
+    }
+
+    #[tokio::test]
+    async fn test_state_is_consumed_once() {
+        let store = MemoryFlowStateStore::default();
+        store.save(flow_state("state")).await.unwrap();
+
+        let (code, state) = store.verify(callback("state")).await.unwrap();
+        assert_eq!(code.secret(), "code");
+        assert_eq!(state.pkce_verifier.secret(), "verifier");
+
+        assert!(matches!(
+            store.verify(callback("state")).await,
+            Err(error::Error::FlowStateNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_expired_state_is_rejected() {
+        let store = MemoryFlowStateStore::new(Duration::from_secs(60));
+
+        let mut state = flow_state("state");
+        state.issued_at = SystemTime::now() - Duration::from_secs(120);
+        store.save(state).await.unwrap();
+
+        assert!(matches!(
+            store.verify(callback("state")).await,
+            Err(error::Error::FlowStateExpired)
+        ));
+        assert!(store.take("state").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_verify_provider() {
+        let state = flow_state("state").with_provider("google");
+        assert!(state.verify_provider(Some("google")).is_ok());
+        assert!(matches!(
+            state.verify_provider(Some("microsoft")),
+            Err(error::Error::FlowStateProviderMismatch)
+        ));
+        assert!(matches!(
+            state.verify_provider(None),
+            Err(error::Error::FlowStateProviderMismatch)
+        ));
+        assert!(flow_state("state").verify_provider(None).is_ok());
+    }
+}
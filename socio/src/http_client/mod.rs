@@ -0,0 +1,201 @@
+#[cfg(feature = "reqwest")]
+pub mod reqwest;
+#[cfg(feature = "ureq")]
+pub mod ureq;
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+};
+
+use async_trait::async_trait;
+use oauth2::AccessToken;
+use serde::de::DeserializeOwned;
+
+use crate::error;
+
+pub type HttpRequest = http::Request<Vec<u8>>;
+pub type HttpResponse = http::Response<Vec<u8>>;
+
+/// Error raised when a request could not be sent or its response could not be read.
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct HttpError(Box<dyn std::error::Error + Send + Sync>);
+
+impl HttpError {
+    pub fn new(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        HttpError(error.into())
+    }
+}
+
+/// Sends the requests made to providers: token exchanges, key sets, discovery and
+/// UserInfo.
+///
+/// Implementations must not follow redirects, since following them opens the client up to
+/// SSRF vulnerabilities. Responses are returned whatever their status.
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse, HttpError>;
+}
+
+/// A cheaply cloneable handle to an [`HttpClient`].
+///
+/// The default client is shared by the whole process. It is backed by `reqwest` when the
+/// `reqwest` feature is enabled, otherwise by `ureq` when the `ureq` feature is.
+#[derive(Clone)]
+pub struct SharedHttpClient(Arc<dyn HttpClient>);
+
+impl SharedHttpClient {
+    pub fn new(client: impl HttpClient + 'static) -> Self {
+        SharedHttpClient(Arc::new(client))
+    }
+
+    pub async fn execute(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+        self.0.execute(request).await
+    }
+
+    /// Sends a `GET` request to a provider API, authorized with the access token, and parses
+    /// the JSON response. `headers` are added to, or replace, the default ones.
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        access_token: &AccessToken,
+        headers: &[(&str, &str)],
+    ) -> error::Result<T> {
+        let response = self
+            .get_with_headers(url, Some(access_token), headers)
+            .await?;
+        parse_json(&response)
+    }
+
+    /// Sends a `GET` request, authorized with the access token when one is given, and
+    /// fails unless the response has a success status.
+    pub async fn get(
+        &self,
+        url: &str,
+        access_token: Option<&AccessToken>,
+    ) -> error::Result<HttpResponse> {
+        self.get_with_headers(url, access_token, &[]).await
+    }
+
+    /// Sends a `POST` request with a form encoded body and fails unless the response has a
+    /// success status.
+    pub async fn post_form(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+    ) -> error::Result<HttpResponse> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+
+        let request = http::Request::post(url)
+            .header(
+                http::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(body.into_bytes())
+            .map_err(HttpError::new)?;
+
+        let response = self.execute(request).await?;
+
+        if !response.status().is_success() {
+            return Err(error::Error::UnexpectedStatus(response.status()));
+        }
+
+        Ok(response)
+    }
+
+    async fn get_with_headers(
+        &self,
+        url: &str,
+        access_token: Option<&AccessToken>,
+        headers: &[(&str, &str)],
+    ) -> error::Result<HttpResponse> {
+        let mut request = http::Request::get(url)
+            .body(Vec::new())
+            .map_err(HttpError::new)?;
+
+        let request_headers = request.headers_mut();
+        request_headers.insert(
+            http::header::ACCEPT,
+            http::HeaderValue::from_static("application/json"),
+        );
+        if let Some(access_token) = access_token {
+            request_headers.insert(
+                http::header::AUTHORIZATION,
+                http::HeaderValue::from_str(&format!("Bearer {}", access_token.secret()))?,
+            );
+        }
+        for (name, value) in headers {
+            request_headers.insert(
+                http::HeaderName::from_bytes(name.as_bytes()).map_err(HttpError::new)?,
+                http::HeaderValue::from_str(value)?,
+            );
+        }
+
+        let response = self.execute(request).await?;
+
+        if !response.status().is_success() {
+            return Err(error::Error::UnexpectedStatus(response.status()));
+        }
+
+        Ok(response)
+    }
+}
+
+pub fn parse_json<T: DeserializeOwned>(response: &HttpResponse) -> error::Result<T> {
+    Ok(serde_json::from_slice(response.body())?)
+}
+
+impl Default for SharedHttpClient {
+    fn default() -> Self {
+        static CLIENT: OnceLock<SharedHttpClient> = OnceLock::new();
+        CLIENT.get_or_init(default_client).clone()
+    }
+}
+
+impl std::fmt::Debug for SharedHttpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedHttpClient").finish_non_exhaustive()
+    }
+}
+
+impl<'c> oauth2::AsyncHttpClient<'c> for SharedHttpClient {
+    type Error = HttpError;
+    type Future = Pin<Box<dyn Future<Output = Result<HttpResponse, HttpError>> + Send + 'c>>;
+
+    fn call(&'c self, request: HttpRequest) -> Self::Future {
+        self.0.execute(request)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+fn default_client() -> SharedHttpClient {
+    SharedHttpClient::new(self::reqwest::ReqwestClient::default())
+}
+
+#[cfg(all(feature = "ureq", not(feature = "reqwest")))]
+fn default_client() -> SharedHttpClient {
+    SharedHttpClient::new(self::ureq::UreqClient::default())
+}
+
+#[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+fn default_client() -> SharedHttpClient {
+    SharedHttpClient::new(Unconfigured)
+}
+
+/// Stands in for the default client when no HTTP client feature is enabled.
+#[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+struct Unconfigured;
+
+#[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+#[async_trait]
+impl HttpClient for Unconfigured {
+    async fn execute(&self, _: HttpRequest) -> Result<HttpResponse, HttpError> {
+        Err(HttpError::new(
+            "No HTTP client available, enable the `reqwest` or `ureq` feature or provide one",
+        ))
+    }
+}
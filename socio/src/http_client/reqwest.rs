@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+
+use super::{HttpClient, HttpError, HttpRequest, HttpResponse};
+
+/// An [`HttpClient`] backed by an async `reqwest` client.
+#[derive(Clone, Debug)]
+pub struct ReqwestClient(reqwest::Client);
+
+impl ReqwestClient {
+    /// Builds a client from a caller configured builder, for timeouts, proxies, root
+    /// certificates or a user agent. The redirect policy of the builder is overridden.
+    pub fn from_builder(builder: reqwest::ClientBuilder) -> Result<Self, HttpError> {
+        let client = builder
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(HttpError::new)?;
+        Ok(ReqwestClient(client))
+    }
+}
+
+impl Default for ReqwestClient {
+    fn default() -> Self {
+        Self::from_builder(reqwest::ClientBuilder::new()).expect("Failed to build HTTP client")
+    }
+}
+
+#[async_trait]
+impl HttpClient for ReqwestClient {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+        let request = reqwest::Request::try_from(request).map_err(HttpError::new)?;
+        let response = self.0.execute(request).await.map_err(HttpError::new)?;
+
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(response.headers().clone());
+        }
+
+        let body = response.bytes().await.map_err(HttpError::new)?;
+        builder.body(body.to_vec()).map_err(HttpError::new)
+    }
+}
@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use ureq::{config::ConfigBuilder, typestate::AgentScope};
+
+use super::{HttpClient, HttpError, HttpRequest, HttpResponse};
+
+/// An [`HttpClient`] backed by a blocking `ureq` agent.
+///
+/// Within a tokio runtime requests run on its blocking thread pool. Under any other
+/// executor they block the thread polling them, which suits command line tools and other
+/// programs driving the futures with a simple executor.
+#[derive(Clone, Debug)]
+pub struct UreqClient(ureq::Agent);
+
+impl UreqClient {
+    /// Builds an agent from a caller configured builder. Redirects are never followed and
+    /// error statuses are returned as regular responses, whatever the builder says.
+    pub fn from_config(config: ConfigBuilder<AgentScope>) -> Self {
+        let config = config
+            .max_redirects(0)
+            .max_redirects_will_error(false)
+            .http_status_as_error(false)
+            .build();
+        UreqClient(ureq::Agent::new_with_config(config))
+    }
+}
+
+impl Default for UreqClient {
+    fn default() -> Self {
+        Self::from_config(ureq::Agent::config_builder())
+    }
+}
+
+#[async_trait]
+impl HttpClient for UreqClient {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let agent = self.0.clone();
+                runtime
+                    .spawn_blocking(move || run(&agent, request))
+                    .await
+                    .map_err(HttpError::new)?
+            }
+            Err(_) => run(&self.0, request),
+        }
+    }
+}
+
+fn run(agent: &ureq::Agent, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+    let response = agent.run(request).map_err(HttpError::new)?;
+
+    let (parts, mut body) = response.into_parts();
+    let body = body.read_to_vec().map_err(HttpError::new)?;
+
+    Ok(http::Response::from_parts(parts, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_redirect_is_returned() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut lines = BufReader::new(&stream).lines();
+            while !lines.next().unwrap().unwrap().is_empty() {}
+            stream
+                .write_all(
+                    b"HTTP/1.1 302 Found\r\nLocation: http://169.254.169.254/\r\n\
+                    Content-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+        });
+
+        let request = http::Request::get(url).body(Vec::new()).unwrap();
+        let response = UreqClient::default().execute(request).await.unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.status(), http::StatusCode::FOUND);
+        assert_eq!(response.headers()["location"], "http://169.254.169.254/");
+    }
+}
@@ -1,9 +1,17 @@
+use std::{future::Future, pin::Pin};
+
 use actix_web::{
-    HttpRequest, HttpResponse, Responder,
+    FromRequest, HttpMessage, HttpRequest, HttpResponse, Responder,
     body::BoxBody,
-    http::{StatusCode, header},
+    dev::Payload,
+    http::{Method, StatusCode, header},
+    web::{Form, Query},
 };
 
+use serde::de::DeserializeOwned;
+
+use crate::integrations::Callback;
+
 #[derive(Debug, Clone)]
 pub struct Redirect {
     url: String,
@@ -24,3 +32,29 @@ impl Responder for Redirect {
             .finish()
     }
 }
+
+/// Extracts the [`Callback`] from a `form_post` body on `POST` requests with a form content
+/// type and from the query otherwise, so one handler serves every response mode.
+///
+/// Providers whose callbacks carry other parameters use their own type in place of
+/// [`Callback`].
+#[derive(Debug)]
+pub struct ExtractCallback<T = Callback>(pub T);
+
+impl<T: DeserializeOwned + 'static> FromRequest for ExtractCallback<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let is_form = req.content_type() == "application/x-www-form-urlencoded";
+        if req.method() != Method::POST || !is_form {
+            let callback = Query::<T>::from_query(req.query_string())
+                .map(|query| ExtractCallback(query.into_inner()))
+                .map_err(Into::into);
+            return Box::pin(async move { callback });
+        }
+
+        let form = Form::<T>::from_request(req, payload);
+        Box::pin(async move { Ok(ExtractCallback(form.await?.into_inner())) })
+    }
+}
@@ -1,7 +1,11 @@
-use axum_core::response::{IntoResponse, Response};
-use http::{HeaderValue, StatusCode, header};
+use axum_core::{
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+};
+use http::{HeaderValue, Method, StatusCode, header};
+use serde::de::DeserializeOwned;
 
-use crate::{error, types::AuthorizationRequest};
+use crate::{error, integrations::Callback, types::AuthorizationRequest};
 
 #[derive(Debug, Clone)]
 pub struct Redirect {
@@ -24,8 +28,90 @@ impl TryFrom<AuthorizationRequest> for Redirect {
     type Error = crate::error::Error;
 
     fn try_from(value: AuthorizationRequest) -> Result<Self, Self::Error> {
-        let header_value = HeaderValue::from_str(value.url.as_str())
-            .map_err(|e| error::Error::HeaderValueError(e))?;
+        let header_value =
+            HeaderValue::from_str(value.url.as_str()).map_err(error::Error::HeaderValueError)?;
         Ok(Redirect::new(header_value))
     }
 }
+
+/// Extracts the [`Callback`] from a `form_post` body on `POST` requests with a form content
+/// type and from the query otherwise, so one handler serves every response mode.
+///
+/// Providers whose callbacks carry other parameters use their own type in place of
+/// [`Callback`].
+#[derive(Debug)]
+pub struct ExtractCallback<T = Callback>(pub T);
+
+impl<S: Send + Sync, T: DeserializeOwned> FromRequest<S> for ExtractCallback<T> {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_form = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+
+        if req.method() != Method::POST || !is_form {
+            let query = req.uri().query().unwrap_or_default();
+            return serde_urlencoded::from_str(query)
+                .map(ExtractCallback)
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()));
+        }
+
+        let body = String::from_request(req, state)
+            .await
+            .map_err(|e| (e.status(), e.body_text()))?;
+
+        serde_urlencoded::from_str(&body)
+            .map(ExtractCallback)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum_core::body::Body;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_extract_form_post_callback() {
+        let request = Request::post("/callback")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from("code=abc&state=xyz&user=%7B%7D"))
+            .unwrap();
+
+        let ExtractCallback(callback) = ExtractCallback::<Callback>::from_request(request, &())
+            .await
+            .unwrap();
+        assert_eq!(callback.code.as_deref(), Some("abc"));
+        assert_eq!(callback.state, "xyz");
+        assert_eq!(callback.extra.get("user").map(String::as_str), Some("{}"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_query_callback() {
+        let request = Request::get("/callback?error=access_denied&state=xyz")
+            .body(Body::empty())
+            .unwrap();
+
+        let ExtractCallback(callback) = ExtractCallback::<Callback>::from_request(request, &())
+            .await
+            .unwrap();
+        assert_eq!(callback.error.as_deref(), Some("access_denied"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_query_callback_on_post() {
+        let request = Request::post("/callback?code=abc&state=xyz")
+            .body(Body::empty())
+            .unwrap();
+
+        let ExtractCallback(callback) = ExtractCallback::<Callback>::from_request(request, &())
+            .await
+            .unwrap();
+        assert_eq!(callback.code.as_deref(), Some("abc"));
+        assert_eq!(callback.state, "xyz");
+    }
+}
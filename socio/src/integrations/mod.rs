@@ -5,23 +5,250 @@ pub mod axum;
 #[cfg(feature = "rocket")]
 pub mod rocket;
 
+use std::collections::HashMap;
+
 use oauth2::{AuthorizationCode, CsrfToken};
 use serde::Deserialize;
 
-use crate::error;
+use crate::{error, flow::FlowState};
 
+/// Parameters of the redirect back from the provider, for both successful and failed
+/// authorizations.
 #[derive(Deserialize, Debug)]
 pub struct Callback {
-    pub code: String,
+    #[serde(default)]
+    pub code: Option<String>,
     pub state: String,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub error_description: Option<String>,
+    #[serde(default)]
+    pub error_uri: Option<String>,
+    /// Issuer of the response, as described in
+    /// [RFC 9207](https://tools.ietf.org/html/rfc9207).
+    #[serde(default)]
+    pub iss: Option<String>,
+    /// Any other parameter the provider sent, such as the `user` field of Sign in with
+    /// Apple.
+    #[serde(flatten)]
+    pub extra: HashMap<String, String>,
 }
 
 impl Callback {
+    /// Creates the callback of a successful authorization.
+    pub fn new(code: impl Into<String>, state: impl Into<String>) -> Self {
+        Callback {
+            code: Some(code.into()),
+            state: state.into(),
+            error: None,
+            error_description: None,
+            error_uri: None,
+            iss: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Verifies the callback against the state of the flow it completes, then returns the
+    /// authorization code or the error the provider redirected with.
+    ///
+    /// Besides the `state` parameter, the `iss` parameter is compared with the issuer of the
+    /// provider that started the flow, which defends against mix-up attacks when several
+    /// providers share a callback.
+    pub fn verify(self, state: &FlowState) -> error::Result<AuthorizationCode> {
+        if self.state != state.csrf_token.secret().as_str() {
+            return Err(error::Error::CsrfTokenMismatch);
+        }
+
+        match (&self.iss, &state.issuer) {
+            (Some(found), Some(expected)) if found != expected => {
+                return Err(error::Error::IssuerMismatch {
+                    expected: expected.clone(),
+                    found: found.clone(),
+                });
+            }
+            (None, _) if state.iss_required => return Err(error::Error::MissingIssuer),
+            _ => {}
+        }
+
+        self.into_authorization_code()
+    }
+
+    /// Verifies the `state` parameter only, then returns the authorization code or the error
+    /// the provider redirected with.
     pub fn verify_csrf_token(self, csrf_token: &CsrfToken) -> error::Result<AuthorizationCode> {
-        if self.state == csrf_token.secret().as_str() {
-            Ok(AuthorizationCode::new(self.code))
-        } else {
-            Err(error::Error::CsrfTokenMismatch)
+        if self.state != csrf_token.secret().as_str() {
+            return Err(error::Error::CsrfTokenMismatch);
+        }
+
+        self.into_authorization_code()
+    }
+
+    fn into_authorization_code(self) -> error::Result<AuthorizationCode> {
+        if let Some(error) = self.error {
+            return Err(error::Error::AuthorizationError(AuthorizationError {
+                kind: AuthorizationErrorKind::from(error.as_str()),
+                description: self.error_description,
+                uri: self.error_uri,
+            }));
+        }
+
+        self.code
+            .map(AuthorizationCode::new)
+            .ok_or(error::Error::MissingAuthorizationCode)
+    }
+}
+
+/// An error returned by the provider instead of an authorization code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthorizationError {
+    pub kind: AuthorizationErrorKind,
+    pub description: Option<String>,
+    pub uri: Option<String>,
+}
+
+impl std::fmt::Display for AuthorizationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.kind.as_str())?;
+        if let Some(description) = &self.description {
+            write!(f, ": {description}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error codes of [RFC 6749, section 4.1.2.1](https://tools.ietf.org/html/rfc6749#section-4.1.2.1)
+/// and [OpenID Connect Core, section 3.1.2.6](https://openid.net/specs/openid-connect-core-1_0.html#AuthError).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthorizationErrorKind {
+    InvalidRequest,
+    UnauthorizedClient,
+    /// The user or the provider denied the request, typically because the user cancelled
+    /// the login.
+    AccessDenied,
+    UnsupportedResponseType,
+    InvalidScope,
+    ServerError,
+    TemporarilyUnavailable,
+    InteractionRequired,
+    LoginRequired,
+    AccountSelectionRequired,
+    ConsentRequired,
+    InvalidRequestUri,
+    InvalidRequestObject,
+    RequestNotSupported,
+    RequestUriNotSupported,
+    RegistrationNotSupported,
+    Other(String),
+}
+
+impl AuthorizationErrorKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AuthorizationErrorKind::InvalidRequest => "invalid_request",
+            AuthorizationErrorKind::UnauthorizedClient => "unauthorized_client",
+            AuthorizationErrorKind::AccessDenied => "access_denied",
+            AuthorizationErrorKind::UnsupportedResponseType => "unsupported_response_type",
+            AuthorizationErrorKind::InvalidScope => "invalid_scope",
+            AuthorizationErrorKind::ServerError => "server_error",
+            AuthorizationErrorKind::TemporarilyUnavailable => "temporarily_unavailable",
+            AuthorizationErrorKind::InteractionRequired => "interaction_required",
+            AuthorizationErrorKind::LoginRequired => "login_required",
+            AuthorizationErrorKind::AccountSelectionRequired => "account_selection_required",
+            AuthorizationErrorKind::ConsentRequired => "consent_required",
+            AuthorizationErrorKind::InvalidRequestUri => "invalid_request_uri",
+            AuthorizationErrorKind::InvalidRequestObject => "invalid_request_object",
+            AuthorizationErrorKind::RequestNotSupported => "request_not_supported",
+            AuthorizationErrorKind::RequestUriNotSupported => "request_uri_not_supported",
+            AuthorizationErrorKind::RegistrationNotSupported => "registration_not_supported",
+            AuthorizationErrorKind::Other(code) => code,
+        }
+    }
+}
+
+impl From<&str> for AuthorizationErrorKind {
+    fn from(value: &str) -> Self {
+        match value {
+            "invalid_request" => AuthorizationErrorKind::InvalidRequest,
+            "unauthorized_client" => AuthorizationErrorKind::UnauthorizedClient,
+            "access_denied" => AuthorizationErrorKind::AccessDenied,
+            "unsupported_response_type" => AuthorizationErrorKind::UnsupportedResponseType,
+            "invalid_scope" => AuthorizationErrorKind::InvalidScope,
+            "server_error" => AuthorizationErrorKind::ServerError,
+            "temporarily_unavailable" => AuthorizationErrorKind::TemporarilyUnavailable,
+            "interaction_required" => AuthorizationErrorKind::InteractionRequired,
+            "login_required" => AuthorizationErrorKind::LoginRequired,
+            "account_selection_required" => AuthorizationErrorKind::AccountSelectionRequired,
+            "consent_required" => AuthorizationErrorKind::ConsentRequired,
+            "invalid_request_uri" => AuthorizationErrorKind::InvalidRequestUri,
+            "invalid_request_object" => AuthorizationErrorKind::InvalidRequestObject,
+            "request_not_supported" => AuthorizationErrorKind::RequestNotSupported,
+            "request_uri_not_supported" => AuthorizationErrorKind::RequestUriNotSupported,
+            "registration_not_supported" => AuthorizationErrorKind::RegistrationNotSupported,
+            other => AuthorizationErrorKind::Other(other.to_string()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_callback() {
+        let callback = serde_json::from_str::<Callback>(
+            r#"{"error":"access_denied","error_description":"User cancelled","state":"state"}"#,
+        )
+        .unwrap();
+
+        let csrf_token = CsrfToken::new("state".to_string());
+        match callback.verify_csrf_token(&csrf_token) {
+            Err(error::Error::AuthorizationError(error)) => {
+                assert_eq!(error.kind, AuthorizationErrorKind::AccessDenied);
+                assert_eq!(error.description.as_deref(), Some("User cancelled"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_issuer_is_verified() {
+        let state = FlowState::new(
+            CsrfToken::new("state".to_string()),
+            oauth2::PkceCodeVerifier::new("verifier".to_string()),
+        )
+        .with_issuer(Some("https://honest.example.com".to_string()), true);
+
+        let callback = |iss: Option<&str>| Callback {
+            iss: iss.map(ToString::to_string),
+            ..Callback::new("code", "state")
+        };
+
+        assert!(
+            callback(Some("https://honest.example.com"))
+                .verify(&state)
+                .is_ok()
+        );
+        assert!(matches!(
+            callback(Some("https://attacker.example.com")).verify(&state),
+            Err(error::Error::IssuerMismatch { .. })
+        ));
+        assert!(matches!(
+            callback(None).verify(&state),
+            Err(error::Error::MissingIssuer)
+        ));
+    }
+
+    #[test]
+    fn test_error_callback_verifies_state() {
+        let callback =
+            serde_json::from_str::<Callback>(r#"{"error":"access_denied","state":"forged"}"#)
+                .unwrap();
+
+        let csrf_token = CsrfToken::new("state".to_string());
+        assert!(matches!(
+            callback.verify_csrf_token(&csrf_token),
+            Err(error::Error::CsrfTokenMismatch)
+        ));
+    }
+}
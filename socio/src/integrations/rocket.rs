@@ -1,5 +1,11 @@
-use crate::types::AuthorizationRequest;
-use std::convert::Infallible;
+use crate::{integrations::Callback, types::AuthorizationRequest};
+use rocket::{
+    Data, Request,
+    data::{self, FromData, Limits},
+    http::Status,
+    request::{self, FromRequest},
+};
+use serde::de::DeserializeOwned;
 use url::Url;
 
 #[derive(Debug, Clone)]
@@ -22,10 +28,110 @@ impl<'r> rocket::response::Responder<'r, 'static> for Redirect {
     }
 }
 
-impl TryFrom<AuthorizationRequest> for Redirect {
-    type Error = Infallible;
+impl From<AuthorizationRequest> for Redirect {
+    fn from(value: AuthorizationRequest) -> Self {
+        Redirect::new(value.url)
+    }
+}
+
+/// Extracts the [`Callback`] from the query as a request guard, or from a `form_post` body
+/// as a data guard on `POST` routes, falling back to the query when there is no form body.
+///
+/// Providers whose callbacks carry other parameters use their own type in place of
+/// [`Callback`].
+#[derive(Debug)]
+pub struct ExtractCallback<T = Callback>(pub T);
+
+fn parse_callback<T: DeserializeOwned>(input: &str) -> Result<ExtractCallback<T>, String> {
+    serde_urlencoded::from_str(input)
+        .map(ExtractCallback)
+        .map_err(|e| e.to_string())
+}
+
+fn parse_query<T: DeserializeOwned>(req: &Request<'_>) -> Result<ExtractCallback<T>, String> {
+    parse_callback(req.uri().query().map(|query| query.as_str()).unwrap_or(""))
+}
+
+#[rocket::async_trait]
+impl<'r, T: DeserializeOwned + Send> FromRequest<'r> for ExtractCallback<T> {
+    type Error = String;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match parse_query(req) {
+            Ok(callback) => request::Outcome::Success(callback),
+            Err(e) => request::Outcome::Error((Status::BadRequest, e)),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: DeserializeOwned + Send> FromData<'r> for ExtractCallback<T> {
+    type Error = String;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        // Without a form body the parameters can only be in the query.
+        if req.content_type().is_none_or(|ct| !ct.is_form()) {
+            return match parse_query(req) {
+                Ok(callback) => data::Outcome::Success(callback),
+                Err(e) => data::Outcome::Error((Status::BadRequest, e)),
+            };
+        }
+
+        let limit = req.limits().get("form").unwrap_or(Limits::FORM);
+        let body = match data.open(limit).into_string().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => {
+                return data::Outcome::Error((
+                    Status::PayloadTooLarge,
+                    format!("Form body is larger than {limit}"),
+                ));
+            }
+            Err(e) => return data::Outcome::Error((Status::BadRequest, e.to_string())),
+        };
+
+        match parse_callback(&body) {
+            Ok(callback) => data::Outcome::Success(callback),
+            Err(e) => data::Outcome::Error((Status::BadRequest, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::local::asynchronous::Client;
+
+    use super::*;
+
+    #[rocket::post("/callback", data = "<callback>")]
+    fn callback(callback: ExtractCallback) -> String {
+        let ExtractCallback(callback) = callback;
+        format!("{} {}", callback.code.unwrap_or_default(), callback.state)
+    }
+
+    async fn client() -> Client {
+        Client::tracked(rocket::build().mount("/", rocket::routes![callback]))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extract_form_post_callback() {
+        let client = client().await;
+        let response = client
+            .post("/callback")
+            .header(rocket::http::ContentType::Form)
+            .body("code=abc&state=xyz")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().await.as_deref(), Some("abc xyz"));
+    }
 
-    fn try_from(value: AuthorizationRequest) -> Result<Self, Self::Error> {
-        Ok(Redirect::new(value.url))
+    #[tokio::test]
+    async fn test_extract_query_callback_on_post() {
+        let client = client().await;
+        let response = client.post("/callback?code=abc&state=xyz").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().await.as_deref(), Some("abc xyz"));
     }
 }
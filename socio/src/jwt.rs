@@ -1,7 +1,23 @@
-use jsonwebtoken::{jwk::JwkSet, DecodingKey, TokenData, Validation};
-use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
 
-use crate::error;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, TokenData, Validation,
+    jwk::{Jwk, JwkSet},
+};
+use oauth2::AccessToken;
+use serde::{Deserialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::{
+    error,
+    http_client::{SharedHttpClient, parse_json},
+    types::{IdTokenSubject, Nonce, SocioClient},
+};
 
 pub async fn verify_jwt_with_jwks_endpoint<T>(
     jwt: &str,
@@ -11,25 +27,550 @@ pub async fn verify_jwt_with_jwks_endpoint<T>(
 where
     T: DeserializeOwned,
 {
-    let header = jsonwebtoken::decode_header(jwt)?;
-    let kid = header
-        .kid
-        .ok_or_else(|| error::Error::Custom("No 'kid' field found found in the jwt".into()))?;
+    JwksCache::shared(jwks_endpoint)
+        .verify(jwt, audience, &SharedHttpClient::default())
+        .await
+}
+
+/// Keys are kept this long when the endpoint does not send a `Cache-Control` max-age.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Minimum time between refetches caused by tokens with an unknown `kid`.
+const DEFAULT_REFRESH_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Caches the key set published at a JWKS endpoint.
+///
+/// Keys are kept for the max-age the endpoint advertises through `Cache-Control`, but never
+/// for less than the refresh cooldown. A token
+/// signed with an unknown `kid` triggers a refetch, at most once per cooldown period, so
+/// that key rotations are picked up without letting forged tokens flood the endpoint.
+/// Concurrent refetches are deduplicated.
+///
+/// The cache does not own an HTTP client, key sets are fetched with the one passed by the
+/// caller.
+#[derive(Debug)]
+pub struct JwksCache {
+    jwks_endpoint: String,
+    refresh_cooldown: Duration,
+    state: RwLock<CachedKeys>,
+    fetch_lock: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Default)]
+struct CachedKeys {
+    keys: Option<Arc<JwkSet>>,
+    fetched_at: Option<Instant>,
+    expires_at: Option<Instant>,
+}
+
+impl CachedKeys {
+    fn is_fresh(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| Instant::now() < expires_at)
+    }
+}
+
+impl JwksCache {
+    pub fn new(jwks_endpoint: impl Into<String>) -> Self {
+        JwksCache {
+            jwks_endpoint: jwks_endpoint.into(),
+            refresh_cooldown: DEFAULT_REFRESH_COOLDOWN,
+            state: RwLock::new(CachedKeys::default()),
+            fetch_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn with_refresh_cooldown(mut self, refresh_cooldown: Duration) -> Self {
+        self.refresh_cooldown = refresh_cooldown;
+        self
+    }
+
+    /// Returns the process wide cache for `jwks_endpoint`, creating it on first use.
+    pub fn shared(jwks_endpoint: &str) -> Arc<JwksCache> {
+        static CACHES: OnceLock<Mutex<HashMap<String, Arc<JwksCache>>>> = OnceLock::new();
+
+        let mut caches = CACHES
+            .get_or_init(Default::default)
+            .lock()
+            .expect("lock poisoned");
+
+        caches
+            .entry(jwks_endpoint.to_string())
+            .or_insert_with(|| Arc::new(JwksCache::new(jwks_endpoint)))
+            .clone()
+    }
+
+    pub fn jwks_endpoint(&self) -> &str {
+        &self.jwks_endpoint
+    }
+
+    /// Returns the key with the given `kid`, fetching the key set when needed.
+    pub async fn find(&self, kid: &str, http_client: &SharedHttpClient) -> error::Result<Jwk> {
+        let (keys, fetched_at) = {
+            let state = self.state.read().expect("lock poisoned");
+            (
+                state.keys.clone().filter(|_| state.is_fresh()),
+                state.fetched_at,
+            )
+        };
+
+        if let Some(keys) = keys {
+            if let Some(jwk) = keys.find(kid) {
+                return Ok(jwk.clone());
+            }
+
+            let cooling_down =
+                fetched_at.is_some_and(|fetched_at| fetched_at.elapsed() < self.refresh_cooldown);
+            if cooling_down {
+                return Err(self.unknown_kid(kid));
+            }
+        }
+
+        let keys = self.refresh(fetched_at, http_client).await?;
+        keys.find(kid).cloned().ok_or_else(|| self.unknown_kid(kid))
+    }
 
-    let jwks = reqwest::get(jwks_endpoint).await?;
-    let jwks = jwks.json::<JwkSet>().await?;
+    pub async fn verify<T>(
+        &self,
+        jwt: &str,
+        audience: &str,
+        http_client: &SharedHttpClient,
+    ) -> error::Result<TokenData<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let header = jsonwebtoken::decode_header(jwt)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| error::Error::Custom("No 'kid' field found found in the jwt".into()))?;
 
-    let jwk = jwks.find(&kid).ok_or_else(|| {
+        let jwk = self.find(&kid, http_client).await?;
+
+        let decoding_key = DecodingKey::from_jwk(&jwk)?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[audience]);
+
+        let token = jsonwebtoken::decode::<T>(jwt, &decoding_key, &validation)?;
+
+        Ok(token)
+    }
+
+    /// Fetches the key set, unless another task already did so since `observed_fetch`.
+    async fn refresh(
+        &self,
+        observed_fetch: Option<Instant>,
+        http_client: &SharedHttpClient,
+    ) -> error::Result<Arc<JwkSet>> {
+        let _guard = self.fetch_lock.lock().await;
+
+        {
+            let state = self.state.read().expect("lock poisoned");
+            if state.fetched_at != observed_fetch
+                && let Some(keys) = &state.keys
+            {
+                return Ok(keys.clone());
+            }
+        }
+
+        let response = http_client.get(&self.jwks_endpoint, None).await?;
+
+        let max_age = response
+            .headers()
+            .get(http::header::CACHE_CONTROL)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_max_age)
+            .unwrap_or(DEFAULT_MAX_AGE)
+            .max(self.refresh_cooldown);
+
+        let keys = Arc::new(parse_json::<JwkSet>(&response)?);
+
+        let now = Instant::now();
+        let mut state = self.state.write().expect("lock poisoned");
+        *state = CachedKeys {
+            keys: Some(keys.clone()),
+            fetched_at: Some(now),
+            expires_at: Some(now + max_age),
+        };
+
+        Ok(keys)
+    }
+
+    fn unknown_kid(&self, kid: &str) -> error::Error {
         error::Error::Custom(
-            format!("No key matching kid '{kid}' found on endpoint: {jwks_endpoint}").into(),
+            format!(
+                "No key matching kid '{kid}' found on endpoint: {}",
+                self.jwks_endpoint
+            )
+            .into(),
         )
-    })?;
+    }
+}
+
+/// Algorithms accepted for ID token signatures unless configured otherwise. Symmetric
+/// algorithms are left out since keys come from a public key set.
+pub const DEFAULT_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// Validates OpenID Connect ID tokens as described in
+/// [OpenID Connect Core, section 3.1.3.7](https://openid.net/specs/openid-connect-core-1_0.html#IDTokenValidation).
+///
+/// The signature, `exp` and `aud` claims are always validated. Every other check is only
+/// performed when configured.
+#[derive(Clone, Debug)]
+pub struct IdTokenValidator {
+    audience: String,
+    issuers: Vec<String>,
+    nonce: Option<String>,
+    subject: Option<IdTokenSubject>,
+    access_token: Option<String>,
+    max_age: Option<Duration>,
+    leeway: Duration,
+    algorithms: Vec<Algorithm>,
+    http_client: SharedHttpClient,
+}
+
+/// Claims the validator checks beyond the ones handled by [`jsonwebtoken`].
+#[derive(Deserialize)]
+struct ValidatedClaims {
+    iss: Option<String>,
+    sub: Option<String>,
+    #[serde(default)]
+    aud: serde_json::Value,
+    azp: Option<String>,
+    nonce: Option<String>,
+    at_hash: Option<String>,
+    auth_time: Option<u64>,
+}
+
+impl IdTokenValidator {
+    pub fn new(audience: impl Into<String>) -> Self {
+        IdTokenValidator {
+            audience: audience.into(),
+            issuers: Vec::new(),
+            nonce: None,
+            subject: None,
+            access_token: None,
+            max_age: None,
+            leeway: Duration::from_secs(60),
+            algorithms: DEFAULT_ALGORITHMS.to_vec(),
+            http_client: SharedHttpClient::default(),
+        }
+    }
+
+    /// Creates a validator expecting the client id as audience and the client's issuer, if
+    /// it is known. Keys are fetched with the client's HTTP client.
+    pub fn for_client(client: &SocioClient) -> Self {
+        let validator =
+            Self::new(client.client_id.as_str()).http_client(client.http_client.clone());
+        match &client.issuer {
+            Some(issuer) => validator.issuer(issuer.clone()),
+            None => validator,
+        }
+    }
+
+    /// Adds an accepted issuer. Tokens from any other issuer are rejected once at least one
+    /// issuer is configured.
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuers.push(issuer.into());
+        self
+    }
+
+    pub fn issuers<I: IntoIterator<Item = String>>(mut self, issuers: I) -> Self {
+        self.issuers.extend(issuers);
+        self
+    }
+
+    /// Requires the `nonce` claim to match the one sent in the authorization request. Passing
+    /// `None` leaves the claim unchecked.
+    pub fn nonce(mut self, nonce: Option<&Nonce>) -> Self {
+        self.nonce = nonce.map(|nonce| nonce.secret().clone());
+        self
+    }
+
+    /// Requires the `iss` and `sub` claims to match those of the ID token from the original
+    /// authentication, as refreshed ID tokens must. Passing `None` leaves them unchecked.
+    pub fn subject(mut self, subject: Option<&IdTokenSubject>) -> Self {
+        self.subject = subject.cloned();
+        self
+    }
+
+    /// Checks the `at_hash` claim, when present, against the access token issued alongside
+    /// the ID token.
+    pub fn access_token(mut self, access_token: &AccessToken) -> Self {
+        self.access_token = Some(access_token.secret().clone());
+        self
+    }
+
+    /// Requires the `auth_time` claim to be no older than `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Allowed clock skew when checking time based claims.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    pub fn algorithms(mut self, algorithms: Vec<Algorithm>) -> Self {
+        self.algorithms = algorithms;
+        self
+    }
+
+    /// HTTP client used to fetch the signing keys.
+    pub fn http_client(mut self, http_client: SharedHttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    pub async fn validate<T>(&self, jwt: &str, jwks: &JwksCache) -> error::Result<TokenData<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let header = jsonwebtoken::decode_header(jwt)?;
+        if !self.algorithms.contains(&header.alg) {
+            return Err(invalid(format!(
+                "algorithm {:?} is not allowed",
+                header.alg
+            )));
+        }
+
+        let kid = header
+            .kid
+            .as_deref()
+            .ok_or_else(|| error::Error::Custom("No 'kid' field found found in the jwt".into()))?;
+
+        let jwk = jwks.find(kid, &self.http_client).await?;
+        let decoding_key = DecodingKey::from_jwk(&jwk)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway.as_secs();
+        validation.set_audience(&[&self.audience]);
+        if !self.issuers.is_empty() {
+            validation.set_issuer(&self.issuers);
+            validation.set_required_spec_claims(&["exp", "aud", "iss"]);
+        } else {
+            validation.set_required_spec_claims(&["exp", "aud"]);
+        }
+
+        let token = jsonwebtoken::decode::<serde_json::Value>(jwt, &decoding_key, &validation)?;
+
+        let claims = serde_json::from_value::<ValidatedClaims>(token.claims.clone())
+            .map_err(|e| invalid(e.to_string()))?;
+        self.validate_claims(header.alg, &claims)?;
+
+        let claims = serde_json::from_value::<T>(token.claims)
+            .map_err(|e| error::Error::Custom(e.into()))?;
+
+        Ok(TokenData {
+            header: token.header,
+            claims,
+        })
+    }
+
+    fn validate_claims(&self, alg: Algorithm, claims: &ValidatedClaims) -> error::Result<()> {
+        if let Some(nonce) = &self.nonce
+            && claims.nonce.as_ref() != Some(nonce)
+        {
+            return Err(invalid("nonce mismatch"));
+        }
+
+        if let Some(subject) = &self.subject {
+            if claims.iss.as_ref() != Some(&subject.issuer) {
+                return Err(invalid("iss differs from the original ID token"));
+            }
+            if claims.sub.as_ref() != Some(&subject.subject) {
+                return Err(invalid("sub differs from the original ID token"));
+            }
+        }
+
+        let audiences = claims.aud.as_array().map_or(1, Vec::len);
+        if audiences > 1 && claims.azp.is_none() {
+            return Err(invalid("azp is required when there are multiple audiences"));
+        }
+
+        if let Some(azp) = &claims.azp
+            && *azp != self.audience
+        {
+            return Err(invalid("azp does not match the client id"));
+        }
+
+        if let (Some(access_token), Some(at_hash)) = (&self.access_token, &claims.at_hash)
+            && token_hash(alg, access_token) != *at_hash
+        {
+            return Err(invalid("at_hash does not match the access token"));
+        }
+
+        if let Some(max_age) = self.max_age {
+            let auth_time = claims
+                .auth_time
+                .ok_or_else(|| invalid("auth_time is required when max_age is set"))?;
+            let now = jsonwebtoken::get_current_timestamp();
+            if now > auth_time + max_age.as_secs() + self.leeway.as_secs() {
+                return Err(invalid("authentication is older than max_age"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Computes an `at_hash`/`c_hash` style hash: the left half of the value's digest, using
+/// the hash function of the signing algorithm.
+pub fn token_hash(alg: Algorithm, value: &str) -> String {
+    let digest = match alg {
+        Algorithm::HS384 | Algorithm::RS384 | Algorithm::PS384 | Algorithm::ES384 => {
+            Sha384::digest(value).to_vec()
+        }
+        Algorithm::HS512 | Algorithm::RS512 | Algorithm::PS512 | Algorithm::EdDSA => {
+            Sha512::digest(value).to_vec()
+        }
+        _ => Sha256::digest(value).to_vec(),
+    };
+
+    URL_SAFE_NO_PAD.encode(&digest[..digest.len() / 2])
+}
+
+fn invalid(reason: impl Into<String>) -> error::Error {
+    error::Error::InvalidIdToken(reason.into())
+}
+
+/// Reads the lifetime of a response from its `Cache-Control` header.
+fn parse_max_age(cache_control: &str) -> Option<Duration> {
+    cache_control.split(',').find_map(|directive| {
+        let directive = directive.trim();
+        if directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+        {
+            return Some(Duration::ZERO);
+        }
+
+        let (name, value) = directive.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("max-age") {
+            value.trim().parse().ok().map(Duration::from_secs)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_max_age() {
+        assert_eq!(
+            parse_max_age("public, max-age=19905, must-revalidate, no-transform"),
+            Some(Duration::from_secs(19905))
+        );
+        assert_eq!(parse_max_age("no-store"), Some(Duration::ZERO));
+        assert_eq!(parse_max_age("public"), None);
+    }
+
+    #[test]
+    fn test_token_hash() {
+        // Example from OpenID Connect Core, appendix A.3.
+        assert_eq!(
+            token_hash(
+                Algorithm::RS256,
+                "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y"
+            ),
+            "77QmUPtjPfzWtF2AnpK9RQ"
+        );
+    }
+
+    #[test]
+    fn test_validate_claims() {
+        let nonce = Nonce::new("n-0S6_WzA2Mj".to_string());
+        let validator = IdTokenValidator::new("client").nonce(Some(&nonce));
+        let claims = |json: &str| serde_json::from_str::<ValidatedClaims>(json).unwrap();
+
+        assert!(
+            validator
+                .validate_claims(Algorithm::RS256, &claims(r#"{"nonce":"n-0S6_WzA2Mj"}"#))
+                .is_ok()
+        );
+        assert!(
+            validator
+                .validate_claims(Algorithm::RS256, &claims(r#"{"nonce":"other"}"#))
+                .is_err()
+        );
+        assert!(
+            validator
+                .validate_claims(
+                    Algorithm::RS256,
+                    &claims(r#"{"nonce":"n-0S6_WzA2Mj","aud":["client","other"]}"#)
+                )
+                .is_err()
+        );
+        assert!(
+            validator
+                .validate_claims(
+                    Algorithm::RS256,
+                    &claims(r#"{"nonce":"n-0S6_WzA2Mj","aud":["client","other"],"azp":"client"}"#)
+                )
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_validate_refreshed_subject() {
+        let subject = IdTokenSubject::new("https://server.example.com", "248289761001");
+        let validator = IdTokenValidator::new("client").subject(Some(&subject));
+        let claims = |json: &str| serde_json::from_str::<ValidatedClaims>(json).unwrap();
 
-    let decoding_key = DecodingKey::from_jwk(jwk)?;
-    let mut validation = Validation::new(header.alg);
-    validation.set_audience(&[audience]);
+        assert!(
+            validator
+                .validate_claims(
+                    Algorithm::RS256,
+                    &claims(r#"{"iss":"https://server.example.com","sub":"248289761001"}"#)
+                )
+                .is_ok()
+        );
+        assert!(
+            validator
+                .validate_claims(
+                    Algorithm::RS256,
+                    &claims(r#"{"iss":"https://server.example.com","sub":"other"}"#)
+                )
+                .is_err()
+        );
+        assert!(
+            validator
+                .validate_claims(
+                    Algorithm::RS256,
+                    &claims(r#"{"iss":"https://other.example.com","sub":"248289761001"}"#)
+                )
+                .is_err()
+        );
+        assert!(
+            validator
+                .validate_claims(Algorithm::RS256, &claims(r#"{"sub":"248289761001"}"#))
+                .is_err()
+        );
+    }
 
-    let token = jsonwebtoken::decode::<T>(jwt, &decoding_key, &validation)?;
+    #[test]
+    fn test_shared_cache_per_endpoint() {
+        let google = JwksCache::shared("https://www.googleapis.com/oauth2/v3/certs");
+        let again = JwksCache::shared("https://www.googleapis.com/oauth2/v3/certs");
+        let facebook = JwksCache::shared("https://www.facebook.com/.well-known/oauth/openid/jwks");
 
-    Ok(token)
+        assert!(Arc::ptr_eq(&google, &again));
+        assert!(!Arc::ptr_eq(&google, &facebook));
+    }
 }
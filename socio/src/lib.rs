@@ -1,20 +1,38 @@
+pub mod discovery;
 pub mod error;
+pub mod flow;
+pub mod http_client;
 pub mod integrations;
 pub mod providers;
 pub mod types;
 
 #[cfg(feature = "jwt")]
 pub mod jwt;
+#[cfg(feature = "oauth1")]
+pub mod oauth1;
+#[cfg(feature = "saml")]
+pub mod saml;
+#[cfg(feature = "sealed-state")]
+pub mod sealed;
+#[cfg(feature = "jwt")]
+pub mod userinfo;
 
 pub use async_trait::async_trait;
 pub use oauth2;
+#[cfg(feature = "reqwest")]
+pub use reqwest;
+#[cfg(feature = "ureq")]
+pub use ureq;
 
+use flow::{FlowState, FlowStateStore};
+use http_client::SharedHttpClient;
+use integrations::Callback;
 use oauth2::{
-    AuthorizationCode, ExtraTokenFields, PkceCodeVerifier, StandardTokenResponse,
-    basic::BasicTokenType,
+    AuthorizationCode, ExtraTokenFields, PkceCodeVerifier, RefreshToken, Scope,
+    StandardRevocableToken, StandardTokenResponse, basic::BasicTokenType,
 };
 use providers::{SocioProvider, UserAwareSocioProvider};
-use types::{AuthorizationRequest, ExtraParams, Response, SocioClient};
+use types::{AuthorizationRequest, ExtraParams, IdTokenSubject, Nonce, Response, SocioClient};
 
 #[derive(Clone, Debug)]
 pub struct Socio<T> {
@@ -27,6 +45,12 @@ impl<T> Socio<T> {
         Socio { config, provider }
     }
 
+    /// Replaces the HTTP client used for every request made to the provider.
+    pub fn with_http_client(mut self, http_client: SharedHttpClient) -> Self {
+        self.config.http_client = http_client;
+        self
+    }
+
     pub fn client(&self) -> &SocioClient {
         &self.config
     }
@@ -53,6 +77,73 @@ impl<T> Socio<T> {
     ) -> error::Result<StandardTokenResponse<Fields, BasicTokenType>> {
         self.client().exchange_code(code, pkce_verifier).await
     }
+
+    pub async fn refresh<Fields: ExtraTokenFields>(
+        &self,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+    ) -> error::Result<StandardTokenResponse<Fields, BasicTokenType>> {
+        self.client().refresh(refresh_token, scopes).await
+    }
+
+    /// Revokes an access or refresh token, as described in
+    /// [RFC 7009](https://tools.ietf.org/html/rfc7009).
+    pub async fn revoke(&self, token: impl Into<StandardRevocableToken>) -> error::Result<()> {
+        self.client().revoke(token.into()).await
+    }
+
+    /// Starts a login, saving the CSRF token and PKCE verifier to `store` until the
+    /// callback arrives.
+    pub async fn begin_login<S: FlowStateStore + ?Sized>(
+        &self,
+        store: &S,
+    ) -> error::Result<AuthorizationRequest> {
+        let request = self.authorize()?;
+        store
+            .save(FlowState::from_authorization_request(&request))
+            .await?;
+        Ok(request)
+    }
+
+    /// Same as [`Socio::begin_login`] but records which provider started the flow, so that
+    /// a callback shared by several providers can tell them apart. Complete it by passing
+    /// the same `provider` to [`Socio::complete_login`].
+    pub async fn begin_login_for_provider<S: FlowStateStore + ?Sized>(
+        &self,
+        store: &S,
+        provider: &str,
+    ) -> error::Result<AuthorizationRequest> {
+        let request = self.authorize()?;
+        store
+            .save(FlowState::from_authorization_request(&request).with_provider(provider))
+            .await?;
+        Ok(request)
+    }
+
+    /// Starts a login without server side state, returning the authorization request along
+    /// with the sealed state to hand to the client, usually as a cookie.
+    #[cfg(feature = "sealed-state")]
+    pub fn begin_sealed_login(
+        &self,
+        sealer: &sealed::StateSealer,
+    ) -> error::Result<(AuthorizationRequest, String)> {
+        let request = self.authorize()?;
+        let sealed = sealer.seal(&FlowState::from_authorization_request(&request))?;
+        Ok((request, sealed))
+    }
+
+    /// Same as [`Socio::begin_sealed_login`] but records which provider started the flow.
+    #[cfg(feature = "sealed-state")]
+    pub fn begin_sealed_login_for_provider(
+        &self,
+        sealer: &sealed::StateSealer,
+        provider: &str,
+    ) -> error::Result<(AuthorizationRequest, String)> {
+        let request = self.authorize()?;
+        let sealed = sealer
+            .seal(&FlowState::from_authorization_request(&request).with_provider(provider))?;
+        Ok((request, sealed))
+    }
 }
 
 impl<T> Socio<T>
@@ -66,30 +157,156 @@ where
 
 impl<T> Socio<T>
 where
-    T: SocioProvider,
+    T: SocioProvider + Sync,
 {
     pub async fn exchange_code_standard(
         &self,
         code: AuthorizationCode,
         pkce_verifier: PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<providers::StandardUser>> {
         self.provider
-            .exchange_code_standard(self.client(), code, pkce_verifier)
+            .exchange_code_standard(self.client(), code, pkce_verifier, nonce)
+            .await
+    }
+
+    /// Refreshes the tokens, validating any ID token the provider returns.
+    ///
+    /// Pass the `id_token_subject` of the login response, a new ID token is then rejected
+    /// unless it names the same issuer and subject.
+    pub async fn refresh_token(
+        &self,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<providers::StandardUser>>> {
+        self.provider
+            .refresh_standard(self.client(), refresh_token, scopes, id_token_subject)
+            .await
+    }
+
+    /// Consumes the state saved by [`Socio::begin_login`] and exchanges the code.
+    ///
+    /// `provider` must be the key the flow was started with by
+    /// [`Socio::begin_login_for_provider`], or `None` for [`Socio::begin_login`], so that a
+    /// flow started for one provider cannot be completed by another.
+    pub async fn complete_login<S: FlowStateStore + ?Sized>(
+        &self,
+        store: &S,
+        mut callback: Callback,
+        provider: Option<&str>,
+    ) -> error::Result<Response<providers::StandardUser>> {
+        let extra = std::mem::take(&mut callback.extra);
+        let (code, state) = store.verify(callback).await?;
+        state.verify_provider(provider)?;
+        self.provider
+            .exchange_callback_standard(
+                self.client(),
+                &extra,
+                code,
+                state.pkce_verifier,
+                state.nonce.as_ref(),
+            )
+            .await
+    }
+
+    /// Verifies the callback against the sealed state from [`Socio::begin_sealed_login`]
+    /// and exchanges the code. See [`Socio::complete_login`] for `provider`.
+    #[cfg(feature = "sealed-state")]
+    pub async fn complete_sealed_login(
+        &self,
+        sealer: &sealed::StateSealer,
+        sealed: &str,
+        mut callback: Callback,
+        provider: Option<&str>,
+    ) -> error::Result<Response<providers::StandardUser>> {
+        let extra = std::mem::take(&mut callback.extra);
+        let (code, state) = callback.verify_sealed_state(sealer, sealed)?;
+        state.verify_provider(provider)?;
+        self.provider
+            .exchange_callback_standard(
+                self.client(),
+                &extra,
+                code,
+                state.pkce_verifier,
+                state.nonce.as_ref(),
+            )
             .await
     }
 }
 
 impl<T> Socio<T>
 where
-    T: UserAwareSocioProvider,
+    T: UserAwareSocioProvider + Sync,
 {
     pub async fn exchange_code_for_user(
         &self,
         code: AuthorizationCode,
         pkce_verifier: PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<T::User>> {
+        self.provider
+            .exchange_code_for_user(self.client(), code, pkce_verifier, nonce)
+            .await
+    }
+
+    /// Refreshes the tokens, returning the provider specific user from any ID token the
+    /// provider returns. See [`Socio::refresh_token`] for `id_token_subject`.
+    pub async fn refresh_token_for_user(
+        &self,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<T::User>>> {
+        self.provider
+            .refresh_for_user(self.client(), refresh_token, scopes, id_token_subject)
+            .await
+    }
+
+    /// Consumes the state saved by [`Socio::begin_login`] and exchanges the code for the
+    /// provider specific user. See [`Socio::complete_login`] for `provider`.
+    pub async fn complete_login_for_user<S: FlowStateStore + ?Sized>(
+        &self,
+        store: &S,
+        mut callback: Callback,
+        provider: Option<&str>,
+    ) -> error::Result<Response<T::User>> {
+        let extra = std::mem::take(&mut callback.extra);
+        let (code, state) = store.verify(callback).await?;
+        state.verify_provider(provider)?;
+        self.provider
+            .exchange_callback_for_user(
+                self.client(),
+                &extra,
+                code,
+                state.pkce_verifier,
+                state.nonce.as_ref(),
+            )
+            .await
+    }
+
+    /// Verifies the callback against the sealed state from [`Socio::begin_sealed_login`]
+    /// and exchanges the code for the provider specific user. See
+    /// [`Socio::complete_login`] for `provider`.
+    #[cfg(feature = "sealed-state")]
+    pub async fn complete_sealed_login_for_user(
+        &self,
+        sealer: &sealed::StateSealer,
+        sealed: &str,
+        mut callback: Callback,
+        provider: Option<&str>,
     ) -> error::Result<Response<T::User>> {
+        let extra = std::mem::take(&mut callback.extra);
+        let (code, state) = callback.verify_sealed_state(sealer, sealed)?;
+        state.verify_provider(provider)?;
         self.provider
-            .exchange_code_for_user(self.client(), code, pkce_verifier)
+            .exchange_callback_for_user(
+                self.client(),
+                &extra,
+                code,
+                state.pkce_verifier,
+                state.nonce.as_ref(),
+            )
             .await
     }
 }
@@ -0,0 +1,545 @@
+//! OAuth 1.0a, as described in [RFC 5849](https://tools.ietf.org/html/rfc5849), for the
+//! legacy providers that have not moved to OAuth 2.0.
+//!
+//! A login goes through three steps:
+//!
+//! 1. [`OAuth1Client::authorize`] obtains a request token and returns the URL to redirect
+//!    the user to. The request token must be kept, usually in the session, until the
+//!    callback arrives.
+//! 2. The provider redirects the user back with a [`Callback`].
+//! 3. [`OAuth1Client::exchange_verifier`] trades the request token and the verifier for an
+//!    access token, which then signs API calls through [`OAuth1Client::sign_request`].
+
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+use oauth2::CsrfToken;
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, pkcs1::DecodeRsaPrivateKey, pkcs8::DecodePrivateKey};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha1::{Digest, Sha1};
+use url::Url;
+
+use crate::{
+    error,
+    http_client::{HttpError, HttpRequest, HttpResponse, SharedHttpClient, parse_json},
+    integrations::{AuthorizationError, AuthorizationErrorKind},
+};
+
+/// How requests are signed.
+#[derive(Clone)]
+pub enum SignatureMethod {
+    /// HMAC-SHA1, keyed with the consumer secret and the token secret.
+    HmacSha1,
+    /// RSA-SHA1, signed with the private key whose public key was registered with the
+    /// provider. The consumer secret is not used.
+    RsaSha1(Box<RsaPrivateKey>),
+}
+
+impl SignatureMethod {
+    /// Reads an RSA-SHA1 private key from a PKCS#8 or PKCS#1 PEM document.
+    pub fn rsa_sha1_from_pem(pem: &str) -> error::Result<Self> {
+        let key = RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .map_err(|e| error::Error::Custom(e.into()))?;
+        Ok(SignatureMethod::RsaSha1(Box::new(key)))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureMethod::HmacSha1 => "HMAC-SHA1",
+            SignatureMethod::RsaSha1(_) => "RSA-SHA1",
+        }
+    }
+}
+
+impl std::fmt::Debug for SignatureMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A token along with its secret, either the temporary request token or the access token.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Token {
+    pub token: String,
+    pub secret: String,
+}
+
+impl Token {
+    pub fn new(token: impl Into<String>, secret: impl Into<String>) -> Self {
+        Token {
+            token: token.into(),
+            secret: secret.into(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Token")
+            .field("token", &self.token)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Access token returned by the provider, along with the other parameters of the response,
+/// such as the `user_id` and `screen_name` some providers add.
+#[derive(Debug)]
+pub struct TokenResponse {
+    pub token: Token,
+    pub params: HashMap<String, String>,
+}
+
+/// Parameters of the redirect back from the provider.
+#[derive(Deserialize, Debug)]
+pub struct Callback {
+    #[serde(default)]
+    pub oauth_token: Option<String>,
+    #[serde(default)]
+    pub oauth_verifier: Option<String>,
+    /// Set instead of the verifier by providers, such as X, when the user denies access.
+    #[serde(default)]
+    pub denied: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct AuthorizationRequest {
+    pub url: Url,
+    /// Request token to keep until the callback arrives, and to pass to
+    /// [`OAuth1Client::exchange_verifier`].
+    pub request_token: Token,
+}
+
+impl AuthorizationRequest {
+    #[cfg(feature = "axum")]
+    pub fn redirect_axum(&self) -> error::Result<crate::integrations::axum::Redirect> {
+        let header_value = http::HeaderValue::from_str(self.url.as_str())
+            .map_err(error::Error::HeaderValueError)?;
+        Ok(crate::integrations::axum::Redirect::new(header_value))
+    }
+
+    #[cfg(feature = "rocket")]
+    pub fn redirect_rocket(&self) -> crate::integrations::rocket::Redirect {
+        crate::integrations::rocket::Redirect::new(self.url.clone())
+    }
+
+    #[cfg(feature = "actix")]
+    pub fn redirect_actix(&self) -> crate::integrations::actix::Redirect {
+        crate::integrations::actix::Redirect::new(self.url.to_string())
+    }
+}
+
+#[derive(Clone)]
+pub struct OAuth1Client {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    /// Endpoint issuing the temporary request tokens.
+    pub request_token_endpoint: Url,
+    /// Endpoint the user is redirected to, to authorize the request token.
+    pub authorize_endpoint: Url,
+    /// Endpoint exchanging an authorized request token for an access token.
+    pub access_token_endpoint: Url,
+    /// URL the provider redirects the user back to.
+    pub callback_url: Url,
+    pub signature_method: SignatureMethod,
+    pub http_client: SharedHttpClient,
+}
+
+impl std::fmt::Debug for OAuth1Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuth1Client")
+            .field("consumer_key", &self.consumer_key)
+            .field("request_token_endpoint", &self.request_token_endpoint)
+            .field("authorize_endpoint", &self.authorize_endpoint)
+            .field("access_token_endpoint", &self.access_token_endpoint)
+            .field("callback_url", &self.callback_url)
+            .field("signature_method", &self.signature_method)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OAuth1Client {
+    pub fn with_http_client(mut self, http_client: SharedHttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Obtains a request token for the callback URL.
+    pub async fn request_token(&self) -> error::Result<Token> {
+        let callback_url = self.callback_url.to_string();
+        let params = self
+            .token_request(
+                &self.request_token_endpoint,
+                None,
+                &[("oauth_callback", &callback_url)],
+            )
+            .await?;
+
+        // Providers that do not confirm the callback implement OAuth 1.0, which is open to
+        // session fixation.
+        if params.get("oauth_callback_confirmed").map(String::as_str) != Some("true") {
+            return Err(error::Error::Custom(
+                "Provider did not confirm the callback URL".into(),
+            ));
+        }
+
+        token_from(params).map(|response| response.token)
+    }
+
+    /// Obtains a request token and builds the URL to redirect the user to.
+    pub async fn authorize(&self) -> error::Result<AuthorizationRequest> {
+        let request_token = self.request_token().await?;
+
+        let mut url = self.authorize_endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("oauth_token", &request_token.token);
+
+        Ok(AuthorizationRequest { url, request_token })
+    }
+
+    /// Checks that the callback is for `request_token` and exchanges it, along with the
+    /// verifier, for an access token.
+    pub async fn exchange_verifier(
+        &self,
+        request_token: &Token,
+        callback: Callback,
+    ) -> error::Result<TokenResponse> {
+        if callback.denied.is_some() {
+            return Err(error::Error::AuthorizationError(AuthorizationError {
+                kind: AuthorizationErrorKind::AccessDenied,
+                description: None,
+                uri: None,
+            }));
+        }
+
+        // The request token plays the part of the OAuth 2.0 state.
+        if callback.oauth_token.as_deref() != Some(request_token.token.as_str()) {
+            return Err(error::Error::CsrfTokenMismatch);
+        }
+
+        let verifier = callback
+            .oauth_verifier
+            .ok_or(error::Error::MissingAuthorizationCode)?;
+
+        let params = self
+            .token_request(
+                &self.access_token_endpoint,
+                Some(request_token),
+                &[("oauth_verifier", &verifier)],
+            )
+            .await?;
+
+        token_from(params)
+    }
+
+    /// Signs `request` with the access token, adding its `Authorization` header.
+    ///
+    /// The query parameters and, for form encoded requests, the body parameters are
+    /// covered by the signature.
+    pub fn sign_request(
+        &self,
+        request: &mut HttpRequest,
+        token: Option<&Token>,
+    ) -> error::Result<()> {
+        let url =
+            Url::parse(&request.uri().to_string()).map_err(|e| error::Error::Custom(e.into()))?;
+
+        let is_form = request
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+        let body_params = match is_form {
+            true => url::form_urlencoded::parse(request.body())
+                .into_owned()
+                .collect(),
+            false => Vec::new(),
+        };
+
+        let header = self.authorization_header(
+            request.method(),
+            &url,
+            &body_params,
+            token,
+            &[],
+            &CsrfToken::new_random().into_secret(),
+            timestamp()?,
+        )?;
+
+        request.headers_mut().insert(
+            http::header::AUTHORIZATION,
+            http::HeaderValue::from_str(&header)?,
+        );
+        Ok(())
+    }
+
+    /// Sends a signed `GET` request to a provider API and parses the JSON response.
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        token: &Token,
+    ) -> error::Result<T> {
+        let mut request = http::Request::get(url)
+            .header(http::header::ACCEPT, "application/json")
+            .body(Vec::new())
+            .map_err(HttpError::new)?;
+        self.sign_request(&mut request, Some(token))?;
+
+        let response = self.execute(request).await?;
+        parse_json(&response)
+    }
+
+    /// Sends a signed `POST` request to a token endpoint and parses the form encoded
+    /// response.
+    async fn token_request(
+        &self,
+        endpoint: &Url,
+        token: Option<&Token>,
+        oauth_params: &[(&str, &str)],
+    ) -> error::Result<HashMap<String, String>> {
+        let header = self.authorization_header(
+            &http::Method::POST,
+            endpoint,
+            &[],
+            token,
+            oauth_params,
+            &CsrfToken::new_random().into_secret(),
+            timestamp()?,
+        )?;
+
+        let request = http::Request::post(endpoint.as_str())
+            .header(http::header::AUTHORIZATION, header)
+            .header(
+                http::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(Vec::new())
+            .map_err(HttpError::new)?;
+
+        let response = self.execute(request).await?;
+
+        Ok(url::form_urlencoded::parse(response.body())
+            .into_owned()
+            .collect())
+    }
+
+    async fn execute(&self, request: HttpRequest) -> error::Result<HttpResponse> {
+        let response = self.http_client.execute(request).await?;
+
+        if !response.status().is_success() {
+            return Err(error::Error::UnexpectedStatus(response.status()));
+        }
+
+        Ok(response)
+    }
+
+    /// Builds the `Authorization` header of a request, signing the protocol parameters
+    /// along with the query of `url` and `body_params`.
+    #[allow(clippy::too_many_arguments)]
+    fn authorization_header(
+        &self,
+        method: &http::Method,
+        url: &Url,
+        body_params: &[(String, String)],
+        token: Option<&Token>,
+        oauth_params: &[(&str, &str)],
+        nonce: &str,
+        timestamp: u64,
+    ) -> error::Result<String> {
+        let timestamp = timestamp.to_string();
+        let mut protocol_params = vec![
+            ("oauth_consumer_key", self.consumer_key.as_str()),
+            ("oauth_nonce", nonce),
+            ("oauth_signature_method", self.signature_method.as_str()),
+            ("oauth_timestamp", timestamp.as_str()),
+        ];
+        if let Some(token) = token {
+            protocol_params.push(("oauth_token", &token.token));
+        }
+        protocol_params.extend_from_slice(oauth_params);
+
+        let params = url
+            .query_pairs()
+            .into_owned()
+            .chain(body_params.iter().cloned())
+            .chain(
+                protocol_params
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string())),
+            )
+            .collect::<Vec<_>>();
+
+        let base_string = base_string(method, url, &params);
+        let signature = self.sign(&base_string, token.map(|token| token.secret.as_str()))?;
+
+        let header = protocol_params
+            .iter()
+            .copied()
+            .chain([("oauth_signature", signature.as_str())])
+            .map(|(name, value)| format!("{name}=\"{}\"", encode(value)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Ok(format!("OAuth {header}"))
+    }
+
+    fn sign(&self, base_string: &str, token_secret: Option<&str>) -> error::Result<String> {
+        let signature = match &self.signature_method {
+            SignatureMethod::HmacSha1 => {
+                let key = format!(
+                    "{}&{}",
+                    encode(&self.consumer_secret),
+                    encode(token_secret.unwrap_or_default())
+                );
+                let mut mac = Hmac::<Sha1>::new_from_slice(key.as_bytes())
+                    .expect("HMAC accepts keys of any size");
+                mac.update(base_string.as_bytes());
+                mac.finalize().into_bytes().to_vec()
+            }
+            SignatureMethod::RsaSha1(key) => key
+                .sign(
+                    Pkcs1v15Sign::new::<Sha1>(),
+                    &Sha1::digest(base_string.as_bytes()),
+                )
+                .map_err(|e| error::Error::Custom(e.into()))?,
+        };
+
+        Ok(STANDARD.encode(signature))
+    }
+}
+
+/// Builds the signature base string of a request, as described in
+/// [RFC 5849 section 3.4.1](https://tools.ietf.org/html/rfc5849#section-3.4.1).
+fn base_string(method: &http::Method, url: &Url, params: &[(String, String)]) -> String {
+    let mut base_url = url.clone();
+    base_url.set_query(None);
+    base_url.set_fragment(None);
+
+    let mut params = params
+        .iter()
+        .map(|(name, value)| (encode(name), encode(value)))
+        .collect::<Vec<_>>();
+    params.sort();
+
+    let normalized = params
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!(
+        "{}&{}&{}",
+        method.as_str(),
+        encode(base_url.as_str()),
+        encode(&normalized)
+    )
+}
+
+/// Percent encodes everything but the unreserved characters of RFC 3986.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn timestamp() -> error::Result<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .map_err(|e| error::Error::Custom(e.into()))
+}
+
+fn token_from(mut params: HashMap<String, String>) -> error::Result<TokenResponse> {
+    let (Some(token), Some(secret)) = (
+        params.remove("oauth_token"),
+        params.remove("oauth_token_secret"),
+    ) else {
+        return Err(error::Error::Custom(
+            "Token response is missing oauth_token or oauth_token_secret".into(),
+        ));
+    };
+
+    Ok(TokenResponse {
+        token: Token { token, secret },
+        params,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(consumer_key: &str, consumer_secret: &str) -> OAuth1Client {
+        let url = Url::parse("https://example.com/").unwrap();
+        OAuth1Client {
+            consumer_key: consumer_key.to_string(),
+            consumer_secret: consumer_secret.to_string(),
+            request_token_endpoint: url.clone(),
+            authorize_endpoint: url.clone(),
+            access_token_endpoint: url.clone(),
+            callback_url: url,
+            signature_method: SignatureMethod::HmacSha1,
+            http_client: SharedHttpClient::default(),
+        }
+    }
+
+    /// Example of RFC 5849 section 3.4.1.1.
+    #[test]
+    fn test_base_string() {
+        let url = Url::parse("http://example.com/request?b5=%3D%253D&a3=a&c%40=&a2=r%20b").unwrap();
+        let params = url
+            .query_pairs()
+            .into_owned()
+            .chain(url::form_urlencoded::parse(b"c2&a3=2+q").into_owned())
+            .chain(
+                [
+                    ("oauth_consumer_key", "9djdj82h48djs9d2"),
+                    ("oauth_token", "kkk9d7dh3k39sjv7"),
+                    ("oauth_signature_method", "HMAC-SHA1"),
+                    ("oauth_timestamp", "137131201"),
+                    ("oauth_nonce", "7d8f3e4a"),
+                ]
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+            )
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            base_string(&http::Method::POST, &url, &params),
+            "POST&http%3A%2F%2Fexample.com%2Frequest&a2%3Dr%2520b%26a3%3D2%2520q%26a3%3Da%26b5%3D%253D%25253D%26c%2540%3D%26c2%3D%26oauth_consumer_key%3D9djdj82h48djs9d2%26oauth_nonce%3D7d8f3e4a%26oauth_signature_method%3DHMAC-SHA1%26oauth_timestamp%3D137131201%26oauth_token%3Dkkk9d7dh3k39sjv7"
+        );
+    }
+
+    /// Example of RFC 5849 section 1.2.
+    #[test]
+    fn test_hmac_sha1_signature() {
+        let client = client("dpf43f3p2l4k3l03", "kd94hf93k423kf44");
+        let token = Token::new("nnch734d00sl2jdk", "pfkkdhi9sl3r4s00");
+        let url =
+            Url::parse("http://photos.example.net/photos?file=vacation.jpg&size=original").unwrap();
+
+        let header = client
+            .authorization_header(
+                &http::Method::GET,
+                &url,
+                &[],
+                Some(&token),
+                &[],
+                "chapoH",
+                137131202,
+            )
+            .unwrap();
+
+        assert!(header.starts_with("OAuth oauth_consumer_key=\"dpf43f3p2l4k3l03\""));
+        assert!(header.contains("oauth_signature=\"MdpQcU8iPSUjWoN%2FUDMsK2sui9I%3D\""));
+    }
+}
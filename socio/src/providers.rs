@@ -1,9 +1,11 @@
+use std::collections::HashMap;
+
 use crate::{
     error,
-    types::{AuthorizationRequest, ExtraParams, Response, SocioClient},
+    types::{AuthorizationRequest, ExtraParams, IdTokenSubject, Nonce, Response, SocioClient},
 };
 use async_trait::async_trait;
-use oauth2::{AuthorizationCode, PkceCodeVerifier};
+use oauth2::{AuthorizationCode, EmptyExtraTokenFields, PkceCodeVerifier, RefreshToken, Scope};
 use serde::{Deserialize, Serialize};
 
 pub type Dynamic = Box<dyn SocioProvider + Sync + Send>;
@@ -31,7 +33,39 @@ pub trait SocioProvider {
         client: &SocioClient,
         code: AuthorizationCode,
         pkce_verifier: PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<StandardUser>>;
+
+    /// Exchanges the code of a verified callback. `extra` holds the other parameters of the
+    /// callback, which some providers use to pass details of the user. The default
+    /// implementation ignores them.
+    async fn exchange_callback_standard(
+        &self,
+        client: &SocioClient,
+        _extra: &HashMap<String, String>,
+        code: AuthorizationCode,
+        pkce_verifier: PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        self.exchange_code_standard(client, code, pkce_verifier, nonce)
+            .await
+    }
+
+    /// Refreshes the tokens. OpenID providers validate the ID token when one is returned,
+    /// requiring the issuer and subject of the original one when `id_token_subject` is
+    /// given. The default implementation never returns a user.
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        _id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        let response = client
+            .refresh::<EmptyExtraTokenFields>(refresh_token, scopes)
+            .await?;
+        Ok(Response::from_standard_token_response(&response, None))
+    }
 }
 
 #[async_trait]
@@ -41,9 +75,35 @@ impl<T: SocioProvider + Sync + ?Sized> SocioProvider for Box<T> {
         client: &SocioClient,
         code: AuthorizationCode,
         pkce_verifier: PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<StandardUser>> {
         self.as_ref()
-            .exchange_code_standard(client, code, pkce_verifier)
+            .exchange_code_standard(client, code, pkce_verifier, nonce)
+            .await
+    }
+
+    async fn exchange_callback_standard(
+        &self,
+        client: &SocioClient,
+        extra: &HashMap<String, String>,
+        code: AuthorizationCode,
+        pkce_verifier: PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        self.as_ref()
+            .exchange_callback_standard(client, extra, code, pkce_verifier, nonce)
+            .await
+    }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        self.as_ref()
+            .refresh_standard(client, refresh_token, scopes, id_token_subject)
             .await
     }
 }
@@ -57,5 +117,36 @@ pub trait UserAwareSocioProvider: SocioProvider {
         client: &SocioClient,
         code: AuthorizationCode,
         pkce_verifier: PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<Self::User>>;
+
+    /// Exchanges the code of a verified callback for the provider specific user. See
+    /// [`SocioProvider::exchange_callback_standard`] for `extra`.
+    async fn exchange_callback_for_user(
+        &self,
+        client: &SocioClient,
+        _extra: &HashMap<String, String>,
+        code: AuthorizationCode,
+        pkce_verifier: PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        self.exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await
+    }
+
+    /// Refreshes the tokens, returning the user from the ID token when one is returned. The
+    /// ID token must carry the issuer and subject of `id_token_subject`, if given. The
+    /// default implementation never returns a user.
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        _id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        let response = client
+            .refresh::<EmptyExtraTokenFields>(refresh_token, scopes)
+            .await?;
+        Ok(Response::from_standard_token_response(&response, None))
+    }
 }
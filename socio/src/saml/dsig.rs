@@ -0,0 +1,437 @@
+//! Verification of enveloped XML signatures, as IdPs sign SAML responses and assertions.
+//!
+//! Only the subset SAML profiles use is supported: a single same-document reference to the
+//! signed element, the enveloped signature and exclusive canonicalization transforms, and
+//! RSA signatures. The key is always taken from the IdP metadata, never from the `KeyInfo`
+//! of the signature.
+
+use std::collections::{BTreeSet, HashMap};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use roxmltree::{Node, NodeId, NodeType};
+use rsa::{Pkcs1v15Sign, RsaPublicKey, pkcs8::DecodePublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::error;
+
+pub(crate) const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+
+/// Public key of an IdP signing certificate.
+#[derive(Clone, Debug)]
+pub struct Certificate {
+    der: Vec<u8>,
+    public_key: RsaPublicKey,
+}
+
+impl Certificate {
+    /// Reads a DER encoded X.509 certificate holding an RSA key.
+    pub fn from_der(der: Vec<u8>) -> error::Result<Self> {
+        let spki = subject_public_key_info(&der)
+            .ok_or_else(|| error::Error::Custom("Malformed X.509 certificate".into()))?;
+        let public_key =
+            RsaPublicKey::from_public_key_der(spki).map_err(|e| error::Error::Custom(e.into()))?;
+
+        Ok(Certificate { der, public_key })
+    }
+
+    /// Reads a certificate from its base64 encoding, as found in metadata
+    /// `X509Certificate` elements.
+    pub fn from_base64(value: &str) -> error::Result<Self> {
+        Certificate::from_der(decode_base64(value)?)
+    }
+
+    pub fn from_pem(pem: &str) -> error::Result<Self> {
+        let body = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        Certificate::from_base64(&body)
+    }
+
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.der)
+    }
+}
+
+/// Verifies the signature `element` carries as a direct child, if any.
+///
+/// Returns `Ok(false)` when the element is not signed, and an error when it is but the
+/// signature does not hold against any of `certificates`.
+pub(crate) fn verify_enveloped(element: Node, certificates: &[Certificate]) -> error::Result<bool> {
+    let Some(signature) = child(element, DSIG_NS, "Signature") else {
+        return Ok(false);
+    };
+
+    let signed_info = child(signature, DSIG_NS, "SignedInfo")
+        .ok_or_else(|| invalid("signature has no SignedInfo"))?;
+
+    let c14n = child(signed_info, DSIG_NS, "CanonicalizationMethod")
+        .ok_or_else(|| invalid("signature has no CanonicalizationMethod"))?;
+    if c14n.attribute("Algorithm") != Some(EXC_C14N) {
+        return Err(invalid("unsupported canonicalization method"));
+    }
+
+    let algorithm = child(signed_info, DSIG_NS, "SignatureMethod")
+        .and_then(|method| method.attribute("Algorithm"))
+        .ok_or_else(|| invalid("signature has no SignatureMethod"))?;
+
+    // Exactly one reference, to the element enveloping the signature, so that what was
+    // verified is what is read afterwards.
+    let mut references = signed_info
+        .children()
+        .filter(|node| node.has_tag_name((DSIG_NS, "Reference")));
+    let reference = references
+        .next()
+        .ok_or_else(|| invalid("signature has no Reference"))?;
+    if references.next().is_some() {
+        return Err(invalid("signature has more than one Reference"));
+    }
+
+    let id = element
+        .attribute("ID")
+        .ok_or_else(|| invalid("signed element has no ID"))?;
+    if reference.attribute("URI") != Some(format!("#{id}").as_str()) {
+        return Err(invalid(
+            "signature does not reference the enveloping element",
+        ));
+    }
+
+    let mut prefixes = Vec::new();
+    if let Some(transforms) = child(reference, DSIG_NS, "Transforms") {
+        for transform in transforms.children().filter(Node::is_element) {
+            match transform.attribute("Algorithm") {
+                Some(ENVELOPED_SIGNATURE) => {}
+                Some(EXC_C14N) => prefixes = inclusive_prefixes(transform),
+                _ => return Err(invalid("unsupported transform")),
+            }
+        }
+    }
+
+    let digest_method = child(reference, DSIG_NS, "DigestMethod")
+        .and_then(|method| method.attribute("Algorithm"))
+        .ok_or_else(|| invalid("reference has no DigestMethod"))?;
+    let digest_value = child(reference, DSIG_NS, "DigestValue")
+        .map(|value| decode_base64(value.text().unwrap_or_default()))
+        .transpose()?
+        .ok_or_else(|| invalid("reference has no DigestValue"))?;
+
+    let canonical = canonicalize(element, Some(signature.id()), &prefixes);
+    if digest(digest_method, canonical.as_bytes())? != digest_value {
+        return Err(invalid("digest does not match the signed element"));
+    }
+
+    let signature_value = child(signature, DSIG_NS, "SignatureValue")
+        .map(|value| decode_base64(value.text().unwrap_or_default()))
+        .transpose()?
+        .ok_or_else(|| invalid("signature has no SignatureValue"))?;
+
+    let canonical = canonicalize(signed_info, None, &inclusive_prefixes(c14n));
+    let (scheme, hashed) = match algorithm {
+        "http://www.w3.org/2000/09/xmldsig#rsa-sha1" => (
+            Pkcs1v15Sign::new::<Sha1>(),
+            Sha1::digest(canonical.as_bytes()).to_vec(),
+        ),
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => (
+            Pkcs1v15Sign::new::<Sha256>(),
+            Sha256::digest(canonical.as_bytes()).to_vec(),
+        ),
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => (
+            Pkcs1v15Sign::new::<Sha512>(),
+            Sha512::digest(canonical.as_bytes()).to_vec(),
+        ),
+        _ => return Err(invalid("unsupported signature method")),
+    };
+
+    certificates
+        .iter()
+        .any(|certificate| {
+            certificate
+                .public_key
+                .verify(scheme.clone(), &hashed, &signature_value)
+                .is_ok()
+        })
+        .then_some(true)
+        .ok_or_else(|| invalid("signature does not match any IdP certificate"))
+}
+
+fn digest(algorithm: &str, data: &[u8]) -> error::Result<Vec<u8>> {
+    match algorithm {
+        "http://www.w3.org/2000/09/xmldsig#sha1" => Ok(Sha1::digest(data).to_vec()),
+        "http://www.w3.org/2001/04/xmlenc#sha256" => Ok(Sha256::digest(data).to_vec()),
+        "http://www.w3.org/2001/04/xmlenc#sha512" => Ok(Sha512::digest(data).to_vec()),
+        _ => Err(invalid("unsupported digest method")),
+    }
+}
+
+/// Prefixes of the `InclusiveNamespaces` of an exclusive canonicalization element.
+fn inclusive_prefixes<'a>(method: Node<'a, '_>) -> Vec<&'a str> {
+    method
+        .children()
+        .find(|node| node.has_tag_name((EXC_C14N, "InclusiveNamespaces")))
+        .and_then(|node| node.attribute("PrefixList"))
+        .map(|list| list.split_whitespace().collect())
+        .unwrap_or_default()
+}
+
+/// Serializes `element` with
+/// [Exclusive XML Canonicalization](https://www.w3.org/TR/xml-exc-c14n/), without
+/// comments, leaving out the `exclude` subtree.
+pub(crate) fn canonicalize(element: Node, exclude: Option<NodeId>, prefixes: &[&str]) -> String {
+    let mut output = String::new();
+    write_element(element, exclude, prefixes, &HashMap::new(), &mut output);
+    output
+}
+
+fn write_element(
+    element: Node,
+    exclude: Option<NodeId>,
+    inclusive: &[&str],
+    rendered: &HashMap<&str, &str>,
+    output: &mut String,
+) {
+    let input = element.document().input_text();
+    let qname = element_qname(element);
+
+    // Namespaces are only rendered where they are visibly utilized, by the element or its
+    // attributes, or listed as inclusive.
+    let mut utilized = BTreeSet::new();
+    utilized.insert(prefix(qname));
+    for attribute in element.attributes() {
+        if attribute.namespace().is_some_and(|ns| ns != XML_NS) {
+            utilized.insert(prefix(&input[attribute.range_qname()]));
+        }
+    }
+    for prefix in inclusive {
+        let prefix = match *prefix {
+            "#default" => "",
+            prefix => prefix,
+        };
+        if lookup(element, prefix).is_some() {
+            utilized.insert(prefix);
+        }
+    }
+
+    let mut rendered = rendered.clone();
+    output.push('<');
+    output.push_str(qname);
+
+    for prefix in utilized {
+        let uri = lookup(element, prefix).unwrap_or_default();
+        let previous = rendered.get(prefix).copied().unwrap_or_default();
+        if prefix == "xml" || previous == uri {
+            continue;
+        }
+
+        match prefix {
+            "" => output.push_str(" xmlns=\""),
+            prefix => {
+                output.push_str(" xmlns:");
+                output.push_str(prefix);
+                output.push_str("=\"");
+            }
+        }
+        escape_attribute(uri, output);
+        output.push('"');
+        rendered.insert(prefix, uri);
+    }
+
+    let mut attributes = element
+        .attributes()
+        .map(|attribute| {
+            (
+                attribute.namespace().unwrap_or_default(),
+                attribute.name(),
+                &input[attribute.range_qname()],
+                attribute.value(),
+            )
+        })
+        .collect::<Vec<_>>();
+    attributes.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    for (_, _, qname, value) in attributes {
+        output.push(' ');
+        output.push_str(qname);
+        output.push_str("=\"");
+        escape_attribute(value, output);
+        output.push('"');
+    }
+    output.push('>');
+
+    for node in element.children() {
+        match node.node_type() {
+            NodeType::Element if Some(node.id()) != exclude => {
+                write_element(node, exclude, inclusive, &rendered, output)
+            }
+            NodeType::Text => escape_text(node.text().unwrap_or_default(), output),
+            NodeType::PI => {
+                if let Some(pi) = node.pi() {
+                    output.push_str("<?");
+                    output.push_str(pi.target);
+                    if let Some(value) = pi.value {
+                        output.push(' ');
+                        output.push_str(value);
+                    }
+                    output.push_str("?>");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    output.push_str("</");
+    output.push_str(qname);
+    output.push('>');
+}
+
+/// Qualified name of an element as written in the document.
+fn element_qname<'a>(element: Node<'_, 'a>) -> &'a str {
+    let start = &element.document().input_text()[element.range().start + 1..];
+    let end = start
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .unwrap_or(start.len());
+    &start[..end]
+}
+
+fn prefix(qname: &str) -> &str {
+    qname.split_once(':').map_or("", |(prefix, _)| prefix)
+}
+
+fn lookup<'a>(element: Node<'a, '_>, prefix: &str) -> Option<&'a str> {
+    match prefix {
+        "" => element.lookup_namespace_uri(None),
+        prefix => element.lookup_namespace_uri(Some(prefix)),
+    }
+}
+
+fn escape_text(value: &str, output: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '\r' => output.push_str("&#xD;"),
+            c => output.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, output: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '"' => output.push_str("&quot;"),
+            '\t' => output.push_str("&#x9;"),
+            '\n' => output.push_str("&#xA;"),
+            '\r' => output.push_str("&#xD;"),
+            c => output.push(c),
+        }
+    }
+}
+
+pub(crate) fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: &str,
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.has_tag_name((namespace, name)))
+}
+
+/// Decodes base64 content of an XML element, which may be wrapped over several lines.
+pub(crate) fn decode_base64(value: &str) -> error::Result<Vec<u8>> {
+    let value = value
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<String>();
+    STANDARD
+        .decode(value)
+        .map_err(|e| error::Error::Custom(e.into()))
+}
+
+/// Finds the `SubjectPublicKeyInfo` of a DER encoded certificate.
+fn subject_public_key_info(der: &[u8]) -> Option<&[u8]> {
+    let (certificate, _) = der_sequence(der)?;
+    let (mut tbs, _) = der_sequence(certificate)?;
+
+    // Skips the optional version, the serial number, the signature algorithm, the issuer,
+    // the validity and the subject.
+    if tbs.first() == Some(&0xa0) {
+        tbs = der_element(tbs)?.1;
+    }
+    for _ in 0..5 {
+        tbs = der_element(tbs)?.1;
+    }
+
+    let (_, rest) = der_element(tbs)?;
+    Some(&tbs[..tbs.len() - rest.len()])
+}
+
+fn der_sequence(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    match input.first() {
+        Some(0x30) => der_element(input),
+        _ => None,
+    }
+}
+
+/// Splits a DER element into its content and the input that follows it.
+fn der_element(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let first = *input.get(1)?;
+    let (length, header) = match first {
+        0..=0x7f => (usize::from(first), 2),
+        0x81..=0x84 => {
+            let count = usize::from(first & 0x7f);
+            let bytes = input.get(2..2 + count)?;
+            let length = bytes
+                .iter()
+                .fold(0usize, |length, byte| (length << 8) | usize::from(*byte));
+            (length, 2 + count)
+        }
+        _ => return None,
+    };
+
+    let end = header.checked_add(length)?;
+    Some((input.get(header..end)?, input.get(end..)?))
+}
+
+fn invalid(reason: &str) -> error::Error {
+    error::Error::InvalidSamlResponse(format!("invalid signature, {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusive_canonicalization() {
+        let xml = "<a:root xmlns:a=\"urn:a\" xmlns:b=\"urn:b\" xmlns:unused=\"urn:unused\">\r\n  \
+            <b:child z=\"1\" b:y=\"2\" a=\"&quot;&#9;\" xmlns=\"urn:default\"><plain/>\
+            <inner xmlns=\"\"/>&lt;&amp;&gt;<!-- comment --></b:child>\n<a:empty/></a:root>";
+        let document = roxmltree::Document::parse(xml).unwrap();
+        let root = document.root_element();
+
+        // Output of `xmllint --exc-c14n` on the same document, less the comment.
+        assert_eq!(
+            canonicalize(root, None, &[]),
+            "<a:root xmlns:a=\"urn:a\">\n  <b:child xmlns:b=\"urn:b\" a=\"&quot;&#x9;\" z=\"1\" \
+            b:y=\"2\"><plain xmlns=\"urn:default\"></plain><inner></inner>&lt;&amp;&gt;\
+            </b:child>\n<a:empty></a:empty></a:root>"
+        );
+
+        let child = root.first_element_child().unwrap();
+        assert_eq!(
+            canonicalize(root, Some(child.id()), &["unused"]),
+            "<a:root xmlns:a=\"urn:a\" xmlns:unused=\"urn:unused\">\n  \n\
+            <a:empty></a:empty></a:root>"
+        );
+    }
+}
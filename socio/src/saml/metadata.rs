@@ -0,0 +1,116 @@
+use roxmltree::{Document, Node};
+use url::Url;
+
+use super::{Binding, METADATA_NS, ServiceProvider, dsig, escape};
+use crate::{error, http_client::SharedHttpClient};
+
+/// What the service provider needs to know about an IdP, usually read from its metadata.
+#[derive(Clone, Debug)]
+pub struct IdpMetadata {
+    pub entity_id: String,
+    /// `SingleSignOnService` endpoint for the HTTP-Redirect binding.
+    pub sso_redirect_url: Option<Url>,
+    /// `SingleSignOnService` endpoint for the HTTP-POST binding.
+    pub sso_post_url: Option<Url>,
+    /// Certificates whose keys may sign responses and assertions. Several are listed while
+    /// the IdP rolls its keys over.
+    pub certificates: Vec<dsig::Certificate>,
+}
+
+impl IdpMetadata {
+    /// Reads the IdP metadata of an `EntityDescriptor`, or of the first `EntityDescriptor`
+    /// with an `IDPSSODescriptor` in an `EntitiesDescriptor`.
+    ///
+    /// Signatures on the metadata itself are not checked, it must come from a trusted
+    /// source.
+    pub fn parse(xml: &str) -> error::Result<Self> {
+        let document = Document::parse(xml).map_err(|e| error::Error::Custom(e.into()))?;
+
+        let (entity, idp) = document
+            .descendants()
+            .filter(|node| node.has_tag_name((METADATA_NS, "EntityDescriptor")))
+            .find_map(|entity| {
+                dsig::child(entity, METADATA_NS, "IDPSSODescriptor").map(|idp| (entity, idp))
+            })
+            .ok_or_else(|| invalid("no IDPSSODescriptor found"))?;
+
+        let entity_id = entity
+            .attribute("entityID")
+            .ok_or_else(|| invalid("EntityDescriptor has no entityID"))?
+            .to_string();
+
+        let sso_url = |binding: Binding| {
+            idp.children()
+                .filter(|node| node.has_tag_name((METADATA_NS, "SingleSignOnService")))
+                .find(|node| node.attribute("Binding") == Some(binding.as_str()))
+                .and_then(|node| node.attribute("Location"))
+                .map(Url::parse)
+                .transpose()
+                .map_err(|e| error::Error::Custom(e.into()))
+        };
+
+        // Keys without a `use` serve for both signing and encryption.
+        let certificates = idp
+            .children()
+            .filter(|node| node.has_tag_name((METADATA_NS, "KeyDescriptor")))
+            .filter(|node| node.attribute("use").is_none_or(|usage| usage == "signing"))
+            .flat_map(|node| node.descendants())
+            .filter(|node| node.has_tag_name((dsig::DSIG_NS, "X509Certificate")))
+            .map(|node: Node| dsig::Certificate::from_base64(node.text().unwrap_or_default()))
+            .collect::<error::Result<Vec<_>>>()?;
+
+        if certificates.is_empty() {
+            return Err(invalid("IDPSSODescriptor has no signing certificate"));
+        }
+
+        Ok(IdpMetadata {
+            entity_id,
+            sso_redirect_url: sso_url(Binding::HttpRedirect)?,
+            sso_post_url: sso_url(Binding::HttpPost)?,
+            certificates,
+        })
+    }
+
+    /// Fetches and parses the metadata the IdP publishes at `url`.
+    pub async fn fetch(url: &str, http_client: &SharedHttpClient) -> error::Result<Self> {
+        let response = http_client.get(url, None).await?;
+        let xml =
+            std::str::from_utf8(response.body()).map_err(|e| error::Error::Custom(e.into()))?;
+        IdpMetadata::parse(xml)
+    }
+
+    /// `SingleSignOnService` endpoint for `binding`.
+    pub fn sso_url(&self, binding: Binding) -> Option<&Url> {
+        match binding {
+            Binding::HttpRedirect => self.sso_redirect_url.as_ref(),
+            Binding::HttpPost => self.sso_post_url.as_ref(),
+        }
+    }
+}
+
+/// Builds the `EntityDescriptor` describing the service provider to IdPs.
+pub(super) fn service_provider(sp: &ServiceProvider) -> String {
+    let name_id_format = sp
+        .name_id_format
+        .as_deref()
+        .map(|format| format!("<md:NameIDFormat>{}</md:NameIDFormat>", escape(format)))
+        .unwrap_or_default();
+
+    format!(
+        "<md:EntityDescriptor xmlns:md=\"{METADATA_NS}\" entityID=\"{entity_id}\">\
+        <md:SPSSODescriptor AuthnRequestsSigned=\"false\" WantAssertionsSigned=\"true\" \
+        protocolSupportEnumeration=\"urn:oasis:names:tc:SAML:2.0:protocol\">\
+        {name_id_format}\
+        <md:AssertionConsumerService Binding=\"{binding}\" Location=\"{acs_url}\" index=\"0\" \
+        isDefault=\"true\"/>\
+        </md:SPSSODescriptor>\
+        </md:EntityDescriptor>",
+        entity_id = escape(&sp.entity_id),
+        binding = Binding::HttpPost.as_str(),
+        acs_url = escape(sp.acs_url.as_str()),
+    )
+}
+
+fn invalid(reason: &str) -> error::Error {
+    error::Error::Custom(format!("Invalid IdP metadata, {reason}").into())
+}
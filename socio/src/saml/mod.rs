@@ -0,0 +1,841 @@
+//! SAML 2.0 service provider, for the Web Browser SSO profile.
+//!
+//! A login goes through two steps:
+//!
+//! 1. [`ServiceProvider::authn_request`] builds the `AuthnRequest` to send the user to the
+//!    IdP with, either as a redirect or as a form. Its id must be kept, usually in the
+//!    session, until the response arrives.
+//! 2. The IdP posts a [`SamlCallback`] to the assertion consumer service, which
+//!    [`ServiceProvider::verify_response`] checks against the IdP metadata and turns into a
+//!    [`Response`], as OAuth logins are.
+//!
+//! Encrypted assertions and signed requests are not supported. Assertion ids are not
+//! remembered, keep them until the assertion expires to refuse replayed responses.
+
+mod dsig;
+mod metadata;
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use flate2::{Compression, write::DeflateEncoder};
+use oauth2::{AccessToken, CsrfToken, basic::BasicTokenType};
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+pub use dsig::Certificate;
+pub use metadata::IdpMetadata;
+
+use crate::{error, providers::StandardUser, types::Response};
+
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const METADATA_NS: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+
+/// [RFC 8693](https://tools.ietf.org/html/rfc8693) token type of SAML 2.0 assertions.
+pub const SAML2_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:saml2";
+
+/// How a message is carried between the service provider and the IdP.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    HttpRedirect,
+    HttpPost,
+}
+
+impl Binding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Binding::HttpRedirect => "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect",
+            Binding::HttpPost => "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ServiceProvider {
+    /// Entity id of the service provider, which assertions must be restricted to.
+    pub entity_id: String,
+    /// Assertion consumer service, the URL the IdP posts responses to.
+    pub acs_url: Url,
+    /// `NameID` format to ask the IdP for, the IdP's choice when unset.
+    pub name_id_format: Option<String>,
+    pub idp: IdpMetadata,
+    /// Whether responses the service provider did not request, from IdP initiated logins,
+    /// are accepted.
+    pub allow_idp_initiated: bool,
+    /// Clock skew tolerated when checking the validity period of assertions.
+    pub clock_skew: Duration,
+    /// Attributes [`ServiceProvider::verify_response_standard`] reads the user from.
+    pub attribute_mapping: AttributeMapping,
+}
+
+/// An `AuthnRequest`, ready to be sent to the IdP.
+#[derive(Clone, Debug)]
+pub struct AuthnRequest {
+    /// Id of the request, to keep until the response arrives and to pass to
+    /// [`ServiceProvider::verify_response`].
+    pub id: String,
+    pub binding: Binding,
+    /// IdP endpoint the request is for.
+    pub destination: Url,
+    pub xml: String,
+    /// Opaque value the IdP sends back with the response.
+    pub relay_state: Option<String>,
+}
+
+/// Parameters the IdP posts to the assertion consumer service.
+#[derive(Deserialize, Debug)]
+pub struct SamlCallback {
+    #[serde(rename = "SAMLResponse")]
+    pub saml_response: String,
+    #[serde(rename = "RelayState", default)]
+    pub relay_state: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SamlUser {
+    /// Entity id of the IdP that issued the assertion.
+    pub issuer: String,
+    pub name_id: String,
+    pub name_id_format: Option<String>,
+    /// Session of the user at the IdP, needed for single logout.
+    pub session_index: Option<String>,
+    /// Values of every attribute of the assertion, by name.
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+impl SamlUser {
+    /// Returns the first value of the attribute `name`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .get(name)
+            .and_then(|values| values.first())
+            .map(String::as_str)
+    }
+
+    pub fn standardize_with(self, mapping: &AttributeMapping) -> StandardUser {
+        let first = |names: &[String]| {
+            names
+                .iter()
+                .find_map(|name| self.attribute(name))
+                .map(str::to_string)
+        };
+
+        let id = match &mapping.id {
+            Some(name) => self.attribute(name).map(str::to_string),
+            None => None,
+        };
+        let email = first(&mapping.email).or_else(|| {
+            (self.name_id_format.as_deref() == Some(EMAIL_ADDRESS_FORMAT))
+                .then(|| self.name_id.clone())
+        });
+
+        StandardUser {
+            name: first(&mapping.name),
+            picture: first(&mapping.picture),
+            email,
+            id: id.unwrap_or(self.name_id),
+        }
+    }
+}
+
+const EMAIL_ADDRESS_FORMAT: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+
+/// Attributes holding the [`StandardUser`] fields. The first attribute of each list that
+/// the assertion has is used.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttributeMapping {
+    /// Attribute holding the user id, the `NameID` when unset.
+    pub id: Option<String>,
+    pub name: Vec<String>,
+    /// Attributes holding the email address. The `NameID` is used when none is present
+    /// and it is an email address.
+    pub email: Vec<String>,
+    pub picture: Vec<String>,
+}
+
+impl Default for AttributeMapping {
+    /// Maps the names IdPs commonly use: the LDAP ones, in both their friendly and OID
+    /// forms, and the claim URIs of Microsoft IdPs.
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        AttributeMapping {
+            id: None,
+            name: names(&[
+                "displayName",
+                "urn:oid:2.16.840.1.113730.3.1.241",
+                "http://schemas.microsoft.com/identity/claims/displayname",
+                "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/name",
+                "cn",
+                "urn:oid:2.5.4.3",
+            ]),
+            email: names(&[
+                "mail",
+                "email",
+                "urn:oid:0.9.2342.19200300.100.1.3",
+                "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/emailaddress",
+            ]),
+            picture: names(&["picture", "jpegPhoto"]),
+        }
+    }
+}
+
+impl From<SamlUser> for StandardUser {
+    fn from(value: SamlUser) -> Self {
+        value.standardize_with(&AttributeMapping::default())
+    }
+}
+
+impl ServiceProvider {
+    pub fn new(entity_id: impl Into<String>, acs_url: Url, idp: IdpMetadata) -> Self {
+        ServiceProvider {
+            entity_id: entity_id.into(),
+            acs_url,
+            name_id_format: None,
+            idp,
+            allow_idp_initiated: false,
+            clock_skew: Duration::from_secs(3 * 60),
+            attribute_mapping: AttributeMapping::default(),
+        }
+    }
+
+    /// Metadata describing the service provider, to register it with IdPs.
+    pub fn metadata(&self) -> String {
+        metadata::service_provider(self)
+    }
+
+    /// Builds an `AuthnRequest` for the IdP `SingleSignOnService` endpoint of `binding`.
+    pub fn authn_request(
+        &self,
+        binding: Binding,
+        relay_state: Option<String>,
+    ) -> error::Result<AuthnRequest> {
+        let destination = self.idp.sso_url(binding).cloned().ok_or_else(|| {
+            error::Error::Custom(
+                format!("IdP has no SingleSignOnService for {}", binding.as_str()).into(),
+            )
+        })?;
+
+        let id = format!("_{}", CsrfToken::new_random().into_secret());
+        let name_id_policy = match &self.name_id_format {
+            Some(format) => format!(
+                "<samlp:NameIDPolicy Format=\"{}\" AllowCreate=\"true\"/>",
+                escape(format)
+            ),
+            None => "<samlp:NameIDPolicy AllowCreate=\"true\"/>".to_string(),
+        };
+
+        let xml = format!(
+            "<samlp:AuthnRequest xmlns:samlp=\"{PROTOCOL_NS}\" xmlns:saml=\"{ASSERTION_NS}\" \
+            ID=\"{id}\" Version=\"2.0\" IssueInstant=\"{issue_instant}\" \
+            Destination=\"{destination}\" AssertionConsumerServiceURL=\"{acs_url}\" \
+            ProtocolBinding=\"{protocol_binding}\">\
+            <saml:Issuer>{issuer}</saml:Issuer>{name_id_policy}</samlp:AuthnRequest>",
+            issue_instant = format_instant(now()?),
+            destination = escape(destination.as_str()),
+            acs_url = escape(self.acs_url.as_str()),
+            protocol_binding = Binding::HttpPost.as_str(),
+            issuer = escape(&self.entity_id),
+        );
+
+        Ok(AuthnRequest {
+            id,
+            binding,
+            destination,
+            xml,
+            relay_state,
+        })
+    }
+
+    /// Verifies the response the IdP posted and returns the user it asserts.
+    ///
+    /// `request_id` is the id of the [`AuthnRequest`] the response answers, `None` for IdP
+    /// initiated logins. The access token of the returned [`Response`] is the base64
+    /// encoded assertion as it appears in the response, of type [`SAML2_TOKEN_TYPE`].
+    pub fn verify_response(
+        &self,
+        callback: &SamlCallback,
+        request_id: Option<&str>,
+    ) -> error::Result<Response<SamlUser>> {
+        self.verify_response_at(&callback.saml_response, request_id, now()?)
+    }
+
+    /// Same as [`ServiceProvider::verify_response`] but returns the user read through the
+    /// attribute mapping.
+    pub fn verify_response_standard(
+        &self,
+        callback: &SamlCallback,
+        request_id: Option<&str>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .verify_response(callback, request_id)?
+            .map_user(|user| user.standardize_with(&self.attribute_mapping)))
+    }
+
+    fn verify_response_at(
+        &self,
+        saml_response: &str,
+        request_id: Option<&str>,
+        now: i64,
+    ) -> error::Result<Response<SamlUser>> {
+        if request_id.is_none() && !self.allow_idp_initiated {
+            return Err(invalid("IdP initiated logins are not allowed"));
+        }
+
+        let xml = String::from_utf8(dsig::decode_base64(saml_response)?)
+            .map_err(|_| invalid("response is not UTF-8"))?;
+        // DTDs are refused by the parser.
+        let document = Document::parse(&xml).map_err(|e| invalid(format!("malformed XML, {e}")))?;
+
+        let response = document.root_element();
+        if !response.has_tag_name((PROTOCOL_NS, "Response")) {
+            return Err(invalid("root element is not a Response"));
+        }
+        if response.attribute("Version") != Some("2.0") {
+            return Err(invalid("unsupported version"));
+        }
+        if response
+            .attribute("Destination")
+            .is_some_and(|destination| destination != self.acs_url.as_str())
+        {
+            return Err(invalid(
+                "Destination does not match the assertion consumer service",
+            ));
+        }
+        if response.attribute("InResponseTo") != request_id {
+            return Err(invalid("InResponseTo does not match the request"));
+        }
+        if let Some(issuer) = dsig::child(response, ASSERTION_NS, "Issuer") {
+            self.check_issuer(issuer)?;
+        }
+
+        let status = dsig::child(response, PROTOCOL_NS, "Status")
+            .and_then(|status| dsig::child(status, PROTOCOL_NS, "StatusCode"));
+        if status.and_then(|code| code.attribute("Value")) != Some(STATUS_SUCCESS) {
+            // The second level code, such as `AuthnFailed`, says more than the first.
+            let code = status
+                .map(|code| dsig::child(code, PROTOCOL_NS, "StatusCode").unwrap_or(code))
+                .and_then(|code| code.attribute("Value"))
+                .unwrap_or("missing");
+            return Err(invalid(format!("IdP returned status {code}")));
+        }
+
+        if dsig::child(response, ASSERTION_NS, "EncryptedAssertion").is_some() {
+            return Err(invalid("encrypted assertions are not supported"));
+        }
+        let mut assertions = response
+            .children()
+            .filter(|node| node.has_tag_name((ASSERTION_NS, "Assertion")));
+        let assertion = assertions
+            .next()
+            .ok_or_else(|| invalid("response has no assertion"))?;
+        if assertions.next().is_some() {
+            return Err(invalid("response has more than one assertion"));
+        }
+
+        // Either signature covers the assertion, the one of the response by enveloping it.
+        let response_signed = dsig::verify_enveloped(response, &self.idp.certificates)?;
+        let assertion_signed = dsig::verify_enveloped(assertion, &self.idp.certificates)?;
+        if !response_signed && !assertion_signed {
+            return Err(invalid("neither the response nor the assertion is signed"));
+        }
+
+        let user = self.check_assertion(assertion, request_id, now)?;
+
+        let expires_in = dsig::child(assertion, ASSERTION_NS, "AuthnStatement")
+            .and_then(|statement| statement.attribute("SessionNotOnOrAfter"))
+            .map(parse_instant)
+            .transpose()?
+            .map(|expiry| Duration::from_secs(expiry.saturating_sub(now).max(0) as u64));
+
+        Ok(Response {
+            access_token: AccessToken::new(STANDARD.encode(&xml[assertion.range()])),
+            token_type: BasicTokenType::Extension(SAML2_TOKEN_TYPE.to_string()),
+            refresh_token: None,
+            expires_in,
+            scopes: None,
+            id_token_subject: None,
+            user,
+        })
+    }
+
+    /// Checks the issuer, subject confirmation and conditions of a verified assertion, and
+    /// reads the user from it.
+    fn check_assertion(
+        &self,
+        assertion: Node,
+        request_id: Option<&str>,
+        now: i64,
+    ) -> error::Result<SamlUser> {
+        let issuer = dsig::child(assertion, ASSERTION_NS, "Issuer")
+            .ok_or_else(|| invalid("assertion has no Issuer"))?;
+        self.check_issuer(issuer)?;
+
+        let subject = dsig::child(assertion, ASSERTION_NS, "Subject")
+            .ok_or_else(|| invalid("assertion has no Subject"))?;
+        let name_id = dsig::child(subject, ASSERTION_NS, "NameID")
+            .ok_or_else(|| invalid("assertion has no NameID"))?;
+
+        let skew = self.clock_skew.as_secs() as i64;
+
+        // At least one bearer confirmation must be for this service provider, this request
+        // and still valid.
+        let mut confirmed = false;
+        for confirmation in subject
+            .children()
+            .filter(|node| node.has_tag_name((ASSERTION_NS, "SubjectConfirmation")))
+            .filter(|node| node.attribute("Method") == Some(BEARER))
+        {
+            let Some(data) = dsig::child(confirmation, ASSERTION_NS, "SubjectConfirmationData")
+            else {
+                continue;
+            };
+            let Some(not_on_or_after) = data.attribute("NotOnOrAfter") else {
+                continue;
+            };
+
+            confirmed = data.attribute("Recipient") == Some(self.acs_url.as_str())
+                && data.attribute("InResponseTo") == request_id
+                && now < parse_instant(not_on_or_after)? + skew;
+            if confirmed {
+                break;
+            }
+        }
+        if !confirmed {
+            return Err(invalid(
+                "assertion has no valid bearer subject confirmation",
+            ));
+        }
+
+        let conditions = dsig::child(assertion, ASSERTION_NS, "Conditions")
+            .ok_or_else(|| invalid("assertion has no Conditions"))?;
+        if let Some(not_before) = conditions.attribute("NotBefore")
+            && now + skew < parse_instant(not_before)?
+        {
+            return Err(invalid("assertion is not valid yet"));
+        }
+        if let Some(not_on_or_after) = conditions.attribute("NotOnOrAfter")
+            && now - skew >= parse_instant(not_on_or_after)?
+        {
+            return Err(invalid("assertion has expired"));
+        }
+
+        // Every audience restriction must include this service provider.
+        let mut restrictions = conditions
+            .children()
+            .filter(|node| node.has_tag_name((ASSERTION_NS, "AudienceRestriction")))
+            .peekable();
+        if restrictions.peek().is_none() {
+            return Err(invalid("assertion has no AudienceRestriction"));
+        }
+        for restriction in restrictions {
+            if !restriction
+                .children()
+                .filter(|node| node.has_tag_name((ASSERTION_NS, "Audience")))
+                .any(|audience| text_content(audience).trim() == self.entity_id)
+            {
+                return Err(invalid(
+                    "service provider is not an audience of the assertion",
+                ));
+            }
+        }
+
+        let mut attributes = HashMap::<String, Vec<String>>::new();
+        for attribute in assertion
+            .children()
+            .filter(|node| node.has_tag_name((ASSERTION_NS, "AttributeStatement")))
+            .flat_map(|statement| statement.children())
+            .filter(|node| node.has_tag_name((ASSERTION_NS, "Attribute")))
+        {
+            let Some(name) = attribute.attribute("Name") else {
+                continue;
+            };
+            attributes.entry(name.to_string()).or_default().extend(
+                attribute
+                    .children()
+                    .filter(|node| node.has_tag_name((ASSERTION_NS, "AttributeValue")))
+                    .map(text_content),
+            );
+        }
+
+        Ok(SamlUser {
+            issuer: self.idp.entity_id.clone(),
+            name_id: text(name_id)?,
+            name_id_format: name_id.attribute("Format").map(str::to_string),
+            session_index: dsig::child(assertion, ASSERTION_NS, "AuthnStatement")
+                .and_then(|statement| statement.attribute("SessionIndex"))
+                .map(str::to_string),
+            attributes,
+        })
+    }
+
+    fn check_issuer(&self, issuer: Node) -> error::Result<()> {
+        let issuer = text(issuer)?;
+        if issuer != self.idp.entity_id {
+            return Err(error::Error::IssuerMismatch {
+                expected: self.idp.entity_id.clone(),
+                found: issuer,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl AuthnRequest {
+    /// URL carrying the request with the HTTP-Redirect binding.
+    pub fn redirect_url(&self) -> error::Result<Url> {
+        if self.binding != Binding::HttpRedirect {
+            return Err(error::Error::Custom(
+                "Request is for the HTTP-POST binding".into(),
+            ));
+        }
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(self.xml.as_bytes())
+            .map_err(|e| error::Error::Custom(e.into()))?;
+        let deflated = encoder
+            .finish()
+            .map_err(|e| error::Error::Custom(e.into()))?;
+
+        let mut url = self.destination.clone();
+        url.query_pairs_mut()
+            .append_pair("SAMLRequest", &STANDARD.encode(deflated));
+        if let Some(relay_state) = &self.relay_state {
+            url.query_pairs_mut().append_pair("RelayState", relay_state);
+        }
+        Ok(url)
+    }
+
+    /// HTML page posting the request to the IdP with the HTTP-POST binding.
+    pub fn post_form(&self) -> error::Result<String> {
+        if self.binding != Binding::HttpPost {
+            return Err(error::Error::Custom(
+                "Request is for the HTTP-Redirect binding".into(),
+            ));
+        }
+
+        let relay_state = self
+            .relay_state
+            .as_deref()
+            .map(|relay_state| {
+                format!(
+                    "<input type=\"hidden\" name=\"RelayState\" value=\"{}\"/>",
+                    escape(relay_state)
+                )
+            })
+            .unwrap_or_default();
+
+        Ok(format!(
+            "<!DOCTYPE html><html><body onload=\"document.forms[0].submit()\">\
+            <form method=\"post\" action=\"{}\">\
+            <input type=\"hidden\" name=\"SAMLRequest\" value=\"{}\"/>{relay_state}\
+            <noscript><button type=\"submit\">Continue</button></noscript>\
+            </form></body></html>",
+            escape(self.destination.as_str()),
+            STANDARD.encode(&self.xml),
+        ))
+    }
+
+    #[cfg(feature = "axum")]
+    pub fn redirect_axum(&self) -> error::Result<crate::integrations::axum::Redirect> {
+        let header_value = http::HeaderValue::from_str(self.redirect_url()?.as_str())
+            .map_err(error::Error::HeaderValueError)?;
+        Ok(crate::integrations::axum::Redirect::new(header_value))
+    }
+
+    #[cfg(feature = "rocket")]
+    pub fn redirect_rocket(&self) -> error::Result<crate::integrations::rocket::Redirect> {
+        Ok(crate::integrations::rocket::Redirect::new(
+            self.redirect_url()?,
+        ))
+    }
+
+    #[cfg(feature = "actix")]
+    pub fn redirect_actix(&self) -> error::Result<crate::integrations::actix::Redirect> {
+        Ok(crate::integrations::actix::Redirect::new(
+            self.redirect_url()?.to_string(),
+        ))
+    }
+}
+
+fn text(node: Node) -> error::Result<String> {
+    Some(text_content(node).trim().to_string())
+        .filter(|text| !text.is_empty())
+        .ok_or_else(|| invalid(format!("{} is empty", node.tag_name().name())))
+}
+
+/// Every text node below `node`, joined.
+///
+/// `Node::text` only returns the first text node, so a comment, which the signature does
+/// not cover, would let anyone cut a signed value short (CVE-2017-11427).
+fn text_content(node: Node) -> String {
+    node.descendants()
+        .filter(Node::is_text)
+        .filter_map(|node| node.text())
+        .collect()
+}
+
+fn now() -> error::Result<i64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() as i64)
+        .map_err(|e| error::Error::Custom(e.into()))
+}
+
+/// Parses an `xs:dateTime`, such as `2025-01-01T00:00:00.000Z`, into a unix timestamp.
+fn parse_instant(value: &str) -> error::Result<i64> {
+    let malformed = || invalid(format!("malformed instant {value}"));
+    let number = |range: std::ops::Range<usize>| -> error::Result<i64> {
+        value
+            .get(range)
+            .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(malformed)
+    };
+
+    if value.get(4..5) != Some("-")
+        || value.get(7..8) != Some("-")
+        || value.get(10..11) != Some("T")
+        || value.get(13..14) != Some(":")
+        || value.get(16..17) != Some(":")
+    {
+        return Err(malformed());
+    }
+
+    let days = days_from_civil(number(0..4)?, number(5..7)?, number(8..10)?);
+    let seconds = days * 86400 + number(11..13)? * 3600 + number(14..16)? * 60 + number(17..19)?;
+
+    // Fractions of a second are dropped, times without a zone are taken as UTC.
+    let zone = value[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match zone {
+        "" | "Z" => 0,
+        zone => {
+            let sign = match zone.get(..1) {
+                Some("+") => 1,
+                Some("-") => -1,
+                _ => return Err(malformed()),
+            };
+            let hours = zone.get(1..3).and_then(|hours| hours.parse::<i64>().ok());
+            let minutes = zone
+                .get(4..6)
+                .and_then(|minutes| minutes.parse::<i64>().ok());
+            match (hours, minutes) {
+                (Some(hours), Some(minutes)) => sign * (hours * 3600 + minutes * 60),
+                _ => return Err(malformed()),
+            }
+        }
+    };
+
+    Ok(seconds - offset)
+}
+
+fn format_instant(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86400));
+    let seconds = timestamp.rem_euclid(86400);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Days since the unix epoch of a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Escapes text and attribute values of generated XML and HTML.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn invalid(reason: impl Into<String>) -> error::Error {
+    error::Error::InvalidSamlResponse(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::DeflateDecoder;
+
+    use super::*;
+
+    const RESPONSE: &str = include_str!("testdata/response.xml");
+    const IDP_METADATA: &str = include_str!("testdata/idp_metadata.xml");
+
+    /// A minute after the fixture assertion was issued.
+    const NOW: i64 = 1735689660;
+
+    fn service_provider() -> ServiceProvider {
+        ServiceProvider::new(
+            "https://sp.example.com/saml/metadata",
+            Url::parse("https://sp.example.com/saml/acs").unwrap(),
+            IdpMetadata::parse(IDP_METADATA).unwrap(),
+        )
+    }
+
+    fn verify(sp: &ServiceProvider, xml: &str, now: i64) -> error::Result<Response<SamlUser>> {
+        sp.verify_response_at(&STANDARD.encode(xml), Some("_request1"), now)
+    }
+
+    #[test]
+    fn test_verify_response() {
+        let sp = service_provider();
+
+        let response = verify(&sp, RESPONSE, NOW).unwrap();
+        assert_eq!(
+            response.expires_in,
+            Some(Duration::from_secs(8 * 3600 - 60))
+        );
+        assert_eq!(response.user.session_index.as_deref(), Some("_session1"));
+        assert_eq!(response.user.attributes["groups"], ["admins", "staff"]);
+
+        let user = StandardUser::from(response.user);
+        assert_eq!(user.id, "jdoe@example.com");
+        assert_eq!(user.name.as_deref(), Some("John & Jane Doe"));
+        assert_eq!(user.email.as_deref(), Some("jdoe@example.com"));
+
+        assert!(verify(&sp, RESPONSE, NOW + 10 * 60).is_err());
+        assert!(verify(&sp, RESPONSE, NOW - 10 * 60).is_err());
+        assert!(
+            sp.verify_response_at(&STANDARD.encode(RESPONSE), Some("_request2"), NOW)
+                .is_err()
+        );
+        assert!(
+            sp.verify_response_at(&STANDARD.encode(RESPONSE), None, NOW)
+                .is_err()
+        );
+
+        let mut other_sp = service_provider();
+        other_sp.entity_id = "https://other.example.com/saml/metadata".to_string();
+        assert!(verify(&other_sp, RESPONSE, NOW).is_err());
+    }
+
+    #[test]
+    fn test_name_id_with_comment() {
+        let sp = service_provider();
+        assert!(RESPONSE.contains("jdoe@<!---->example.com</saml:NameID>"));
+
+        let response = verify(&sp, RESPONSE, NOW).unwrap();
+        assert_eq!(response.user.name_id, "jdoe@example.com");
+    }
+
+    #[test]
+    fn test_tampered_response() {
+        let sp = service_provider();
+
+        let tampered = RESPONSE.replace(">jdoe@<!---->example.com<", ">admin@example.com<");
+        assert!(matches!(
+            verify(&sp, &tampered, NOW),
+            Err(error::Error::InvalidSamlResponse(_))
+        ));
+
+        // An unsigned assertion added next to the signed one.
+        let assertion_start = RESPONSE.find("<saml:Assertion").unwrap();
+        let assertion_end = RESPONSE.find("</saml:Assertion>").unwrap() + 17;
+        let mut wrapped = RESPONSE.to_string();
+        wrapped.insert_str(
+            assertion_start,
+            &RESPONSE[assertion_start..assertion_end].replace("_assertion1", "_assertion2"),
+        );
+        assert!(verify(&sp, &wrapped, NOW).is_err());
+
+        let unsigned = RESPONSE[..RESPONSE.find("<ds:Signature").unwrap()].to_string()
+            + &RESPONSE[RESPONSE.find("</ds:Signature>").unwrap() + 15..];
+        assert!(verify(&sp, &unsigned, NOW).is_err());
+    }
+
+    #[test]
+    fn test_authn_request() {
+        let sp = service_provider();
+        let request = sp
+            .authn_request(Binding::HttpRedirect, Some("state".to_string()))
+            .unwrap();
+        let url = request.redirect_url().unwrap();
+        assert!(
+            url.as_str()
+                .starts_with("https://idp.example.com/sso/redirect?")
+        );
+
+        let params = url.query_pairs().collect::<HashMap<_, _>>();
+        assert_eq!(params["RelayState"], "state");
+
+        let mut xml = String::new();
+        DeflateDecoder::new(
+            STANDARD
+                .decode(params["SAMLRequest"].as_bytes())
+                .unwrap()
+                .as_slice(),
+        )
+        .read_to_string(&mut xml)
+        .unwrap();
+        assert_eq!(xml, request.xml);
+
+        let document = Document::parse(&xml).unwrap();
+        let root = document.root_element();
+        assert_eq!(root.attribute("ID"), Some(request.id.as_str()));
+        assert_eq!(
+            root.attribute("AssertionConsumerServiceURL"),
+            Some("https://sp.example.com/saml/acs")
+        );
+        let issue_instant = parse_instant(root.attribute("IssueInstant").unwrap()).unwrap();
+        assert!((now().unwrap() - issue_instant).abs() <= 1);
+
+        assert!(request.post_form().is_err());
+    }
+
+    #[test]
+    fn test_instants() {
+        assert_eq!(parse_instant("2025-01-01T00:01:00Z").unwrap(), NOW);
+        assert_eq!(parse_instant("2025-01-01T01:01:00.250+01:00").unwrap(), NOW);
+        assert_eq!(format_instant(NOW), "2025-01-01T00:01:00Z");
+        assert_eq!(format_instant(951782400), "2000-02-29T00:00:00Z");
+        assert!(parse_instant("2025-01-01").is_err());
+    }
+}
@@ -0,0 +1,343 @@
+use std::time::Duration;
+
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng, Payload},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use oauth2::AuthorizationCode;
+
+use crate::{
+    error,
+    flow::FlowState,
+    integrations::Callback,
+    types::{ResponseMode, SocioClient},
+};
+
+const NONCE_LEN: usize = 12;
+
+/// Binds sealed values to their purpose, so they cannot be swapped with other values sealed
+/// under the same key.
+const ASSOCIATED_DATA: &[u8] = b"socio.flow_state.v1";
+
+/// A 256-bit key used to seal [`FlowState`] with AES-256-GCM.
+#[derive(Clone)]
+pub struct SealingKey(Key<Aes256Gcm>);
+
+impl SealingKey {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        SealingKey(bytes.into())
+    }
+
+    pub fn generate() -> Self {
+        SealingKey(Aes256Gcm::generate_key(OsRng))
+    }
+
+    /// Reads a key from its url-safe, unpadded base64 representation.
+    pub fn from_base64(value: &str) -> error::Result<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(value).map_err(|e| {
+            error::Error::InvalidConfiguration(format!("sealing key is not base64, {e}"))
+        })?;
+        let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+            error::Error::InvalidConfiguration("sealing keys must be 32 bytes long".to_string())
+        })?;
+        Ok(SealingKey::new(bytes))
+    }
+
+    pub fn to_base64(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.0)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.0)
+    }
+}
+
+impl std::fmt::Debug for SealingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SealingKey([redacted])")
+    }
+}
+
+/// Seals [`FlowState`] into an opaque value the client carries, typically in a cookie, so no
+/// server side storage is needed between the redirect and the callback.
+///
+/// Values are sealed with the first key. Every key is tried when unsealing, which allows
+/// rotating in a new key while values sealed with the previous one are still in flight.
+#[derive(Clone, Debug)]
+pub struct StateSealer {
+    keys: Vec<SealingKey>,
+    max_age: Duration,
+}
+
+impl StateSealer {
+    pub fn new(key: SealingKey) -> Self {
+        StateSealer {
+            keys: vec![key],
+            max_age: Duration::from_secs(10 * 60),
+        }
+    }
+
+    /// Adds a key that is only used to unseal values.
+    pub fn with_decryption_key(mut self, key: SealingKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    pub fn seal(&self, state: &FlowState) -> error::Result<String> {
+        let plaintext = serde_json::to_vec(state)?;
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.keys[0]
+            .cipher()
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: ASSOCIATED_DATA,
+                },
+            )
+            .map_err(|_| error::Error::InvalidSealedState)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+
+        Ok(URL_SAFE_NO_PAD.encode(sealed))
+    }
+
+    pub fn unseal(&self, sealed: &str) -> error::Result<FlowState> {
+        let sealed = URL_SAFE_NO_PAD
+            .decode(sealed)
+            .map_err(|_| error::Error::InvalidSealedState)?;
+
+        if sealed.len() < NONCE_LEN {
+            return Err(error::Error::InvalidSealedState);
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::from_slice(nonce);
+
+        let plaintext = self
+            .keys
+            .iter()
+            .find_map(|key| {
+                key.cipher()
+                    .decrypt(
+                        nonce,
+                        Payload {
+                            msg: ciphertext,
+                            aad: ASSOCIATED_DATA,
+                        },
+                    )
+                    .ok()
+            })
+            .ok_or(error::Error::InvalidSealedState)?;
+
+        let state = serde_json::from_slice::<FlowState>(&plaintext)
+            .map_err(|_| error::Error::InvalidSealedState)?;
+
+        if state.is_expired(self.max_age) {
+            return Err(error::Error::FlowStateExpired);
+        }
+
+        Ok(state)
+    }
+}
+
+impl Callback {
+    /// Unseals the state carried by the client and verifies the callback against it.
+    pub fn verify_sealed_state(
+        self,
+        sealer: &StateSealer,
+        sealed: &str,
+    ) -> error::Result<(AuthorizationCode, FlowState)> {
+        let state = sealer.unseal(sealed)?;
+        let code = self.verify(&state)?;
+        Ok((code, state))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// Attributes of the cookie carrying the sealed state.
+///
+/// `SameSite=Lax` is the default since the callback is a top-level navigation from the
+/// provider back to the application. Browsers do not send `Lax` cookies with the cross-site
+/// `POST` of the `form_post` response mode, which needs `SameSite=None` instead, see
+/// [`StateCookie::for_client`].
+#[derive(Clone, Debug)]
+pub struct StateCookie {
+    pub name: String,
+    pub path: String,
+    pub secure: bool,
+    pub same_site: SameSite,
+}
+
+impl Default for StateCookie {
+    fn default() -> Self {
+        StateCookie::new("socio_state")
+    }
+}
+
+impl StateCookie {
+    pub fn new(name: impl Into<String>) -> Self {
+        StateCookie {
+            name: name.into(),
+            path: "/".to_string(),
+            secure: true,
+            same_site: SameSite::Lax,
+        }
+    }
+
+    /// Returns the default cookie, switched to `SameSite=None` when the client uses the
+    /// `form_post` response mode.
+    pub fn for_client(client: &SocioClient) -> Self {
+        let mut cookie = StateCookie::default();
+        if client.response_mode == Some(ResponseMode::FormPost) {
+            cookie.same_site = SameSite::None;
+            cookie.secure = true;
+        }
+        cookie
+    }
+
+    /// Returns a `Set-Cookie` header value storing `sealed` for `max_age`.
+    pub fn set_cookie(&self, sealed: &str, max_age: Duration) -> String {
+        let mut cookie = format!(
+            "{}={}; Path={}; Max-Age={}; HttpOnly; SameSite={}",
+            self.name,
+            sealed,
+            self.path,
+            max_age.as_secs(),
+            self.same_site.as_str()
+        );
+
+        // Browsers reject `SameSite=None` cookies that are not `Secure`.
+        if self.secure || self.same_site == SameSite::None {
+            cookie.push_str("; Secure");
+        }
+
+        cookie
+    }
+
+    /// Returns a `Set-Cookie` header value that removes the cookie.
+    pub fn remove_cookie(&self) -> String {
+        self.set_cookie("", Duration::ZERO)
+    }
+
+    /// Finds the value of this cookie in a `Cookie` request header.
+    pub fn find<'a>(&self, cookie_header: &'a str) -> Option<&'a str> {
+        cookie_header
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(name, _)| *name == self.name)
+            .map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oauth2::{CsrfToken, PkceCodeVerifier};
+
+    use super::*;
+
+    fn flow_state() -> FlowState {
+        FlowState::new(
+            CsrfToken::new("state".to_string()),
+            PkceCodeVerifier::new("verifier".to_string()),
+        )
+        .with_provider("google")
+    }
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let sealer = StateSealer::new(SealingKey::generate());
+
+        let sealed = sealer.seal(&flow_state()).unwrap();
+        let state = sealer.unseal(&sealed).unwrap();
+
+        assert_eq!(state.csrf_token.secret(), "state");
+        assert_eq!(state.pkce_verifier.secret(), "verifier");
+        assert_eq!(state.provider.as_deref(), Some("google"));
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old_key = SealingKey::generate();
+        let sealed = StateSealer::new(old_key.clone())
+            .seal(&flow_state())
+            .unwrap();
+
+        let rotated = StateSealer::new(SealingKey::generate()).with_decryption_key(old_key);
+        assert!(rotated.unseal(&sealed).is_ok());
+
+        let unrelated = StateSealer::new(SealingKey::generate());
+        assert!(matches!(
+            unrelated.unseal(&sealed),
+            Err(error::Error::InvalidSealedState)
+        ));
+    }
+
+    #[test]
+    fn test_expired_state_is_rejected() {
+        let sealer = StateSealer::new(SealingKey::generate()).with_max_age(Duration::from_secs(60));
+
+        let mut state = flow_state();
+        state.issued_at -= Duration::from_secs(120);
+        let sealed = sealer.seal(&state).unwrap();
+
+        assert!(matches!(
+            sealer.unseal(&sealed),
+            Err(error::Error::FlowStateExpired)
+        ));
+    }
+
+    #[test]
+    fn test_key_from_base64() {
+        let key = SealingKey::generate();
+        assert_eq!(
+            SealingKey::from_base64(&key.to_base64())
+                .unwrap()
+                .to_base64(),
+            key.to_base64()
+        );
+
+        assert!(matches!(
+            SealingKey::from_base64(&URL_SAFE_NO_PAD.encode([0; 16])),
+            Err(error::Error::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_cookie() {
+        let cookie = StateCookie::default();
+        assert_eq!(
+            cookie.find("theme=dark; socio_state=abc; other=1"),
+            Some("abc")
+        );
+        assert_eq!(cookie.find("theme=dark"), None);
+    }
+}
@@ -1,25 +1,27 @@
 use std::{borrow::Cow, time::Duration};
 
 use oauth2::{
-    AccessToken, AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken,
-    EmptyExtraTokenFields, EndpointNotSet, EndpointSet, ExtraTokenFields, PkceCodeVerifier,
-    RedirectUrl, RefreshToken, Scope, StandardRevocableToken, StandardTokenResponse, TokenResponse,
-    TokenUrl,
+    AccessToken, AuthType, AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken,
+    EmptyExtraTokenFields, EndpointMaybeSet, EndpointNotSet, EndpointSet, ExtraTokenFields,
+    PkceCodeVerifier, RedirectUrl, RefreshToken, RevocationUrl, Scope, StandardRevocableToken,
+    StandardTokenResponse, TokenResponse, TokenUrl,
     basic::{
         BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
         BasicTokenType,
     },
 };
-use oauth2_reqwest::ReqwestClient;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use crate::{error, providers::StandardUser};
+use crate::{
+    discovery::ProviderMetadata, error, http_client::SharedHttpClient, providers::StandardUser,
+};
 
 pub type CustomClient<
     Fields = EmptyExtraTokenFields,
     HasAuthUrl = EndpointSet,
     HasTokenUrl = EndpointSet,
+    HasRevocationUrl = EndpointMaybeSet,
 > = Client<
     BasicErrorResponse,
     StandardTokenResponse<Fields, BasicTokenType>,
@@ -29,7 +31,7 @@ pub type CustomClient<
     HasAuthUrl,
     EndpointNotSet,
     EndpointNotSet,
-    EndpointNotSet,
+    HasRevocationUrl,
     HasTokenUrl,
 >;
 
@@ -40,7 +42,68 @@ pub struct OpenIdTokenField {
 
 impl ExtraTokenFields for OpenIdTokenField {}
 
+/// Token fields of responses that may carry an ID token, such as refresh responses.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OptionalOpenIdTokenField {
+    #[serde(default)]
+    pub id_token: Option<String>,
+}
+
+impl ExtraTokenFields for OptionalOpenIdTokenField {}
+
+/// Value of the OpenID Connect `nonce` parameter, used to bind an ID token to the
+/// authorization request that produced it.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Nonce(String);
+
+impl Nonce {
+    pub fn new(nonce: String) -> Self {
+        Nonce(nonce)
+    }
+
+    /// Generates a new random, base64-encoded 128-bit nonce.
+    pub fn new_random() -> Self {
+        Nonce(CsrfToken::new_random().into_secret())
+    }
+
+    pub fn secret(&self) -> &String {
+        &self.0
+    }
+
+    pub fn into_secret(self) -> String {
+        self.0
+    }
+}
+
+impl std::fmt::Debug for Nonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Nonce([redacted])")
+    }
+}
+
+/// Issuer and subject of the ID token from the original authentication.
+///
+/// ID tokens returned by a refresh must carry the same values, as described in
+/// [OpenID Connect Core, section 12.2](https://openid.net/specs/openid-connect-core-1_0.html#RefreshTokenResponse).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdTokenSubject {
+    pub issuer: String,
+    pub subject: String,
+}
+
+impl IdTokenSubject {
+    pub fn new(issuer: impl Into<String>, subject: impl Into<String>) -> Self {
+        IdTokenSubject {
+            issuer: issuer.into(),
+            subject: subject.into(),
+        }
+    }
+}
+
+/// Client configuration for a provider, built with [`SocioClient::new`] and the `with_*`
+/// methods.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct SocioClient {
     pub client_id: ClientId,
     pub client_secret: ClientSecret,
@@ -48,15 +111,170 @@ pub struct SocioClient {
     pub token_endpoint: TokenUrl,
     pub scopes: Vec<Scope>,
     pub redirect_uri: RedirectUrl,
+    /// Identifier of the OpenID provider, when known.
+    pub issuer: Option<String>,
+    /// [RFC 7009](https://tools.ietf.org/html/rfc7009) token revocation endpoint, for
+    /// providers that have one.
+    pub revocation_endpoint: Option<RevocationUrl>,
+    /// OpenID Connect UserInfo endpoint. When set, providers complete the ID token claims
+    /// with the ones it returns.
+    pub userinfo_endpoint: Option<Url>,
+    /// Whether the provider sends the [RFC 9207](https://tools.ietf.org/html/rfc9207) `iss`
+    /// parameter with its callbacks, in which case callbacks without it are rejected.
+    pub iss_parameter_supported: bool,
+    /// How the provider should return the callback parameters, the provider's default when
+    /// unset.
+    pub response_mode: Option<ResponseMode>,
+    /// How the client credentials are sent to the token endpoint.
+    pub auth_type: AuthType,
+    /// Parameters added to every authorization request, for provider specific options.
+    pub extra_params: Vec<(String, String)>,
+    pub http_client: SharedHttpClient,
 }
 
 impl SocioClient {
+    /// Creates a client for the given endpoints, without scopes and with the defaults of
+    /// every other setting. The `with_*` methods set the rest.
+    pub fn new(
+        client_id: ClientId,
+        client_secret: ClientSecret,
+        redirect_uri: RedirectUrl,
+        authorize_endpoint: AuthUrl,
+        token_endpoint: TokenUrl,
+    ) -> Self {
+        SocioClient {
+            client_id,
+            client_secret,
+            authorize_endpoint,
+            token_endpoint,
+            scopes: Vec::new(),
+            redirect_uri,
+            issuer: None,
+            revocation_endpoint: None,
+            userinfo_endpoint: None,
+            iss_parameter_supported: false,
+            response_mode: None,
+            auth_type: AuthType::BasicAuth,
+            extra_params: Vec::new(),
+            http_client: SharedHttpClient::default(),
+        }
+    }
+
+    /// Creates a client from the endpoints and scopes advertised in provider metadata.
+    pub fn from_metadata(
+        metadata: &ProviderMetadata,
+        client_id: ClientId,
+        client_secret: ClientSecret,
+        redirect_uri: RedirectUrl,
+    ) -> Self {
+        SocioClient {
+            scopes: metadata.default_scopes(),
+            issuer: Some(metadata.issuer.clone()),
+            revocation_endpoint: metadata.revocation_endpoint.clone(),
+            userinfo_endpoint: metadata.userinfo_endpoint.clone(),
+            iss_parameter_supported: metadata.authorization_response_iss_parameter_supported,
+            auth_type: metadata.auth_type(),
+            ..SocioClient::new(
+                client_id,
+                client_secret,
+                redirect_uri,
+                metadata.authorization_endpoint.clone(),
+                metadata.token_endpoint.clone(),
+            )
+        }
+    }
+
+    /// Creates a client by discovering the metadata published by `issuer`, using the
+    /// default HTTP client.
+    pub async fn discover(
+        issuer: &str,
+        client_id: ClientId,
+        client_secret: ClientSecret,
+        redirect_uri: RedirectUrl,
+    ) -> error::Result<Self> {
+        Self::discover_with(
+            issuer,
+            client_id,
+            client_secret,
+            redirect_uri,
+            SharedHttpClient::default(),
+        )
+        .await
+    }
+
+    /// Creates a client by discovering the metadata published by `issuer`, making the
+    /// discovery request and every later one with `http_client`.
+    pub async fn discover_with(
+        issuer: &str,
+        client_id: ClientId,
+        client_secret: ClientSecret,
+        redirect_uri: RedirectUrl,
+        http_client: SharedHttpClient,
+    ) -> error::Result<Self> {
+        let metadata = ProviderMetadata::discover(issuer, &http_client).await?;
+        Ok(
+            Self::from_metadata(&metadata, client_id, client_secret, redirect_uri)
+                .with_http_client(http_client),
+        )
+    }
+
+    pub fn with_scopes<S: Into<String>>(mut self, scopes: impl IntoIterator<Item = S>) -> Self {
+        self.scopes = scopes
+            .into_iter()
+            .map(|scope| Scope::new(scope.into()))
+            .collect();
+        self
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    pub fn with_revocation_endpoint(mut self, revocation_endpoint: RevocationUrl) -> Self {
+        self.revocation_endpoint = Some(revocation_endpoint);
+        self
+    }
+
+    pub fn with_userinfo_endpoint(mut self, userinfo_endpoint: Url) -> Self {
+        self.userinfo_endpoint = Some(userinfo_endpoint);
+        self
+    }
+
+    pub fn with_iss_parameter_supported(mut self, iss_parameter_supported: bool) -> Self {
+        self.iss_parameter_supported = iss_parameter_supported;
+        self
+    }
+
+    pub fn with_response_mode(mut self, response_mode: ResponseMode) -> Self {
+        self.response_mode = Some(response_mode);
+        self
+    }
+
+    pub fn with_auth_type(mut self, auth_type: AuthType) -> Self {
+        self.auth_type = auth_type;
+        self
+    }
+
+    /// Adds a parameter to every authorization request.
+    pub fn with_extra_param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_params.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_http_client(mut self, http_client: SharedHttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
     pub fn client<Fields: ExtraTokenFields>(self) -> CustomClient<Fields> {
-        CustomClient::<Fields, EndpointNotSet, EndpointNotSet>::new(self.client_id)
+        CustomClient::<Fields, EndpointNotSet, EndpointNotSet, EndpointNotSet>::new(self.client_id)
             .set_client_secret(self.client_secret)
             .set_auth_uri(self.authorize_endpoint)
             .set_token_uri(self.token_endpoint)
+            .set_revocation_url_option(self.revocation_endpoint)
             .set_redirect_uri(self.redirect_uri)
+            .set_auth_type(self.auth_type)
     }
 
     pub fn authorize(&self, params: Option<ExtraParams>) -> error::Result<AuthorizationRequest> {
@@ -70,6 +288,19 @@ impl SocioClient {
             .add_scopes(self.scopes.clone())
             .set_pkce_challenge(pkce_challenge);
 
+        let nonce = self.is_openid().then(Nonce::new_random);
+        if let Some(nonce) = &nonce {
+            request = request.add_extra_param("nonce", nonce.secret());
+        }
+
+        if let Some(response_mode) = &self.response_mode {
+            request = request.add_extra_param("response_mode", response_mode.as_str());
+        }
+
+        for (key, value) in &self.extra_params {
+            request = request.add_extra_param(key, value);
+        }
+
         if let Some(params) = params {
             for (key, value) in params.0 {
                 request = request.add_extra_param(key, value);
@@ -82,9 +313,17 @@ impl SocioClient {
             url,
             csrf_token,
             pkce_verifier,
+            nonce,
+            issuer: self.issuer.clone(),
+            iss_required: self.iss_parameter_supported,
         })
     }
 
+    /// Whether the client requests the `openid` scope, making this an OpenID Connect flow.
+    pub fn is_openid(&self) -> bool {
+        self.scopes.iter().any(|scope| scope.as_str() == "openid")
+    }
+
     pub async fn exchange_code<Fields: ExtraTokenFields>(
         &self,
         code: AuthorizationCode,
@@ -92,20 +331,72 @@ impl SocioClient {
     ) -> error::Result<StandardTokenResponse<Fields, BasicTokenType>> {
         let client = self.clone().client::<Fields>();
 
-        let http_client: ReqwestClient = reqwest::ClientBuilder::new()
-            // Following redirects opens the client up to SSRF vulnerabilities.
-            .redirect(reqwest::redirect::Policy::none())
-            .build()?
-            .into();
-
         let response = client
             .exchange_code(code)
             .set_pkce_verifier(pkce_verifier)
-            .request_async(&http_client)
+            .request_async(&self.http_client)
             .await?;
 
         Ok(response)
     }
+
+    /// Exchanges a refresh token for new tokens, optionally narrowing the granted scopes.
+    ///
+    /// Providers that do not rotate refresh tokens return none, in which case the given
+    /// refresh token is kept in the response.
+    pub async fn refresh<Fields: ExtraTokenFields>(
+        &self,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+    ) -> error::Result<StandardTokenResponse<Fields, BasicTokenType>> {
+        let client = self.clone().client::<Fields>();
+
+        let mut response = client
+            .exchange_refresh_token(refresh_token)
+            .add_scopes(scopes.unwrap_or_default())
+            .request_async(&self.http_client)
+            .await?;
+
+        if response.refresh_token().is_none() {
+            response.set_refresh_token(Some(refresh_token.clone()));
+        }
+
+        Ok(response)
+    }
+
+    /// Revokes an access or refresh token at the revocation endpoint.
+    pub async fn revoke(&self, token: StandardRevocableToken) -> error::Result<()> {
+        let client = self.clone().client::<EmptyExtraTokenFields>();
+
+        client
+            .revoke_token(token)?
+            .request_async(&self.http_client)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// How the provider returns the callback parameters, as described in
+/// [OAuth 2.0 Multiple Response Type Encoding Practices](https://openid.net/specs/oauth-v2-multiple-response-types-1_0.html#ResponseModes)
+/// and [OAuth 2.0 Form Post Response Mode](https://openid.net/specs/oauth-v2-form-post-response-mode-1_0.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseMode {
+    /// Parameters are added to the query of the redirect URI.
+    Query,
+    /// Parameters are POSTed to the redirect URI as a form, which keeps the code out of
+    /// browser history and access logs.
+    FormPost,
+}
+
+impl ResponseMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseMode::Query => "query",
+            ResponseMode::FormPost => "form_post",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -113,13 +404,19 @@ pub struct AuthorizationRequest {
     pub url: Url,
     pub pkce_verifier: PkceCodeVerifier,
     pub csrf_token: CsrfToken,
+    /// Nonce sent with OpenID Connect requests, to be checked against the ID token.
+    pub nonce: Option<Nonce>,
+    /// Issuer expected in the `iss` parameter of the callback.
+    pub issuer: Option<String>,
+    /// Whether the callback must carry the `iss` parameter.
+    pub iss_required: bool,
 }
 
 impl AuthorizationRequest {
     #[cfg(feature = "axum")]
     pub fn redirect_axum(&self) -> error::Result<crate::integrations::axum::Redirect> {
         let header_value = http::HeaderValue::from_str(self.url.as_str())
-            .map_err(|e| error::Error::HeaderValueError(e))?;
+            .map_err(error::Error::HeaderValueError)?;
         Ok(crate::integrations::axum::Redirect::new(header_value))
     }
 
@@ -141,12 +438,14 @@ pub struct Response<Claims> {
     pub refresh_token: Option<RefreshToken>,
     pub expires_in: Option<Duration>,
     pub scopes: Option<Vec<Scope>>,
+    /// Issuer and subject of the validated ID token, to be passed back when refreshing.
+    pub id_token_subject: Option<IdTokenSubject>,
     pub user: Claims,
 }
 
 impl<Claims> Response<Claims> {
-    pub fn from_standard_token_response(
-        response: &StandardTokenResponse<OpenIdTokenField, BasicTokenType>,
+    pub fn from_standard_token_response<Fields: ExtraTokenFields>(
+        response: &StandardTokenResponse<Fields, BasicTokenType>,
         claims: Claims,
     ) -> Self {
         Response {
@@ -155,22 +454,56 @@ impl<Claims> Response<Claims> {
             refresh_token: response.refresh_token().cloned(),
             expires_in: response.expires_in(),
             scopes: response.scopes().cloned(),
+            id_token_subject: None,
             user: claims,
         }
     }
-}
 
-impl<T: Into<StandardUser>> Response<T> {
-    pub fn standardize(self) -> Response<StandardUser> {
+    /// Records the issuer and subject of the ID token the user was read from.
+    pub fn with_id_token_subject(mut self, id_token_subject: Option<IdTokenSubject>) -> Self {
+        self.id_token_subject = id_token_subject;
+        self
+    }
+
+    pub fn map_user<U>(self, f: impl FnOnce(Claims) -> U) -> Response<U> {
         Response {
             access_token: self.access_token,
             token_type: self.token_type,
             refresh_token: self.refresh_token,
             expires_in: self.expires_in,
             scopes: self.scopes,
-            user: self.user.into(),
+            id_token_subject: self.id_token_subject,
+            user: f(self.user),
         }
     }
+
+    pub fn try_map_user<U, E>(
+        self,
+        f: impl FnOnce(Claims) -> Result<U, E>,
+    ) -> Result<Response<U>, E> {
+        let user = f(self.user)?;
+        Ok(Response {
+            access_token: self.access_token,
+            token_type: self.token_type,
+            refresh_token: self.refresh_token,
+            expires_in: self.expires_in,
+            scopes: self.scopes,
+            id_token_subject: self.id_token_subject,
+            user,
+        })
+    }
+}
+
+impl<T: Into<StandardUser>> Response<T> {
+    pub fn standardize(self) -> Response<StandardUser> {
+        self.map_user(Into::into)
+    }
+}
+
+impl<T: Into<StandardUser>> Response<Option<T>> {
+    pub fn standardize_optional(self) -> Response<Option<StandardUser>> {
+        self.map_user(|user| user.map(Into::into))
+    }
 }
 
 #[derive(Clone, Debug)]
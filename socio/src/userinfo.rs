@@ -0,0 +1,135 @@
+use oauth2::{AccessToken, ConfigurationError};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+
+use crate::{error, http_client::parse_json, types::SocioClient};
+
+impl SocioClient {
+    /// Fetches the claims about the user from the OpenID Connect UserInfo endpoint.
+    ///
+    /// Only plain JSON responses are supported, signed or encrypted responses are rejected.
+    pub async fn userinfo(&self, access_token: &AccessToken) -> error::Result<Map<String, Value>> {
+        let endpoint = self
+            .userinfo_endpoint
+            .as_ref()
+            .ok_or(ConfigurationError::MissingUrl("userinfo"))?;
+
+        let response = self
+            .http_client
+            .get(endpoint.as_str(), Some(access_token))
+            .await?;
+
+        parse_json(&response)
+    }
+
+    /// Completes the ID token claims with the ones from the UserInfo endpoint, when the
+    /// client has one configured.
+    pub async fn merge_userinfo<T: Serialize + DeserializeOwned>(
+        &self,
+        access_token: &AccessToken,
+        claims: T,
+    ) -> error::Result<T> {
+        if self.userinfo_endpoint.is_none() {
+            return Ok(claims);
+        }
+
+        let userinfo = self.userinfo(access_token).await?;
+        merge_claims(claims, userinfo)
+    }
+}
+
+/// Fills the claims missing from the ID token with the UserInfo claims.
+///
+/// Claims present in the ID token are kept as they are, since only those are signed. The
+/// UserInfo response is rejected when its `sub` differs from the ID token `sub`, as it may
+/// then describe another user.
+pub fn merge_claims<T: Serialize + DeserializeOwned>(
+    claims: T,
+    userinfo: Map<String, Value>,
+) -> error::Result<T> {
+    let Value::Object(mut merged) =
+        serde_json::to_value(claims).map_err(|e| error::Error::Custom(e.into()))?
+    else {
+        return Err(error::Error::Custom(
+            "ID token claims must be a JSON object".into(),
+        ));
+    };
+
+    if userinfo.get("sub").is_none() || userinfo.get("sub") != merged.get("sub") {
+        return Err(error::Error::UserInfoSubjectMismatch);
+    }
+
+    for (name, value) in userinfo {
+        let entry = merged.entry(name).or_insert(Value::Null);
+        if entry.is_null() {
+            *entry = value;
+        }
+    }
+
+    serde_json::from_value(Value::Object(merged)).map_err(|e| error::Error::Custom(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct User {
+        sub: String,
+        name: Option<String>,
+        picture: Option<String>,
+    }
+
+    fn userinfo(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_merge_claims() {
+        let user = User {
+            sub: "123".to_string(),
+            name: Some("From ID token".to_string()),
+            picture: None,
+        };
+
+        let merged = merge_claims(
+            user,
+            userinfo(json!({
+                "sub": "123",
+                "name": "From UserInfo",
+                "picture": "https://example.com/picture.png",
+            })),
+        )
+        .unwrap();
+
+        assert_eq!(merged.name.as_deref(), Some("From ID token"));
+        assert_eq!(
+            merged.picture.as_deref(),
+            Some("https://example.com/picture.png")
+        );
+    }
+
+    #[test]
+    fn test_subject_mismatch_is_rejected() {
+        let user = || User {
+            sub: "123".to_string(),
+            name: None,
+            picture: None,
+        };
+
+        assert!(matches!(
+            merge_claims(user(), userinfo(json!({ "sub": "456" }))),
+            Err(error::Error::UserInfoSubjectMismatch)
+        ));
+        assert!(matches!(
+            merge_claims(user(), userinfo(json!({ "name": "No subject" }))),
+            Err(error::Error::UserInfoSubjectMismatch)
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use socio::{
+    Socio, async_trait, error,
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, RevocationUrl, Scope, TokenUrl,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{IdTokenSubject, Nonce, Response, SocioClient},
+};
+use url::Url;
+
+use crate::openid::OpenId;
+
+/// An Auth0 tenant.
+#[derive(Clone, Debug)]
+pub struct Auth0 {
+    pub openid: OpenId,
+}
+
+impl Auth0 {
+    /// `domain` is the custom domain when the tenant has one, since tokens requested through
+    /// it carry it as issuer.
+    pub fn new(domain: &str) -> error::Result<Self> {
+        Ok(Auth0 {
+            openid: OpenId::new(endpoint(domain, ".well-known/jwks.json")?),
+        })
+    }
+}
+
+/// Issuer of the tokens, which Auth0 ends with a slash.
+pub fn issuer(domain: &str) -> String {
+    format!("https://{domain}/")
+}
+
+fn endpoint(domain: &str, path: &str) -> error::Result<Url> {
+    Url::parse(&issuer(domain))
+        .and_then(|url| url.join(path))
+        .map_err(|e| error::Error::Custom(e.into()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Auth0User {
+    pub iss: String,
+    pub sub: String,
+    pub name: Option<String>,
+    pub nickname: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub picture: Option<String>,
+    /// Every other claim, including the namespaced custom claims added by Actions.
+    #[serde(flatten)]
+    pub claims: Map<String, Value>,
+}
+
+impl Auth0User {
+    /// Returns a custom claim, such as `https://example.com/roles`.
+    pub fn claim(&self, name: &str) -> Option<&Value> {
+        self.claims.get(name)
+    }
+}
+
+#[async_trait]
+impl SocioProvider for Auth0 {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Auth0 {
+    type User = Auth0User;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        self.openid
+            .exchange_code_as(client, code, pkce_verifier, nonce)
+            .await
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        self.openid
+            .refresh_as(client, refresh_token, scopes, id_token_subject)
+            .await
+    }
+}
+
+impl From<Auth0User> for StandardUser {
+    fn from(value: Auth0User) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.name.or(value.nickname),
+            email: value.email,
+            picture: value.picture,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Auth0Config {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+    /// Domain of the tenant, such as `acme.eu.auth0.com`.
+    pub domain: String,
+    /// Custom domain of the tenant, used instead of `domain` when set.
+    #[serde(default)]
+    pub custom_domain: Option<String>,
+    /// Identifier of the API the access token should be issued for.
+    #[serde(default)]
+    pub audience: Option<String>,
+}
+
+impl Auth0Config {
+    fn domain(&self) -> &str {
+        self.custom_domain.as_deref().unwrap_or(&self.domain)
+    }
+}
+
+impl TryFrom<Auth0Config> for SocioClient {
+    type Error = error::Error;
+
+    fn try_from(value: Auth0Config) -> Result<Self, Self::Error> {
+        let domain = &value.domain().to_string();
+
+        let client = SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(endpoint(domain, "authorize")?),
+            TokenUrl::from_url(endpoint(domain, "oauth/token")?),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_issuer(issuer(domain))
+        .with_revocation_endpoint(RevocationUrl::from_url(endpoint(domain, "oauth/revoke")?))
+        .with_userinfo_endpoint(endpoint(domain, "userinfo")?);
+
+        Ok(match value.audience {
+            Some(audience) => client.with_extra_param("audience", audience),
+            None => client,
+        })
+    }
+}
+
+impl TryFrom<Auth0Config> for Socio<Auth0> {
+    type Error = error::Error;
+
+    fn try_from(value: Auth0Config) -> Result<Self, Self::Error> {
+        let auth0 = Auth0::new(value.domain())?;
+        Ok(Socio::new(value.try_into()?, auth0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_domain() {
+        let client = SocioClient::try_from(Auth0Config {
+            client_id: ClientId::new("app".to_string()),
+            client_secret: ClientSecret::new("secret".to_string()),
+            redirect_url: RedirectUrl::new("https://app.example.com/callback".to_string()).unwrap(),
+            domain: "acme.eu.auth0.com".to_string(),
+            custom_domain: Some("login.acme.com".to_string()),
+            audience: Some("https://api.acme.com".to_string()),
+        })
+        .unwrap();
+
+        assert_eq!(client.issuer.as_deref(), Some("https://login.acme.com/"));
+        assert_eq!(
+            client.token_endpoint.as_str(),
+            "https://login.acme.com/oauth/token"
+        );
+        assert_eq!(
+            client.extra_params,
+            [("audience".to_string(), "https://api.acme.com".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_custom_claims() {
+        let user = serde_json::from_str::<Auth0User>(
+            r#"{
+                "iss": "https://acme.eu.auth0.com/",
+                "sub": "auth0|123",
+                "nickname": "jdoe",
+                "https://acme.com/roles": ["admin"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            user.claim("https://acme.com/roles"),
+            Some(&serde_json::json!(["admin"]))
+        );
+        assert_eq!(StandardUser::from(user).name.as_deref(), Some("jdoe"));
+    }
+}
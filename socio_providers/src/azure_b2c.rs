@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use socio::{
+    Socio, async_trait, error,
+    oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, Scope, TokenUrl},
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{IdTokenSubject, Nonce, Response, SocioClient},
+};
+use url::Url;
+
+use crate::openid::OpenId;
+
+/// An Azure AD B2C user flow or custom policy.
+#[derive(Clone, Debug)]
+pub struct AzureB2c {
+    pub openid: OpenId,
+}
+
+/// Location of an Azure AD B2C policy, from which every URL is derived.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct B2cPolicy {
+    /// Name of the tenant, such as `contoso` for `contoso.onmicrosoft.com`.
+    pub tenant_name: String,
+    /// Id of the tenant, which is part of the token issuer.
+    pub tenant_id: String,
+    /// Name of the user flow or custom policy, such as `B2C_1_signupsignin`.
+    pub policy: String,
+    /// Custom domain in front of the tenant, `<tenant name>.b2clogin.com` when unset.
+    #[serde(default)]
+    pub custom_domain: Option<String>,
+}
+
+impl B2cPolicy {
+    fn host(&self) -> String {
+        match &self.custom_domain {
+            Some(domain) => domain.clone(),
+            None => format!("{}.b2clogin.com", self.tenant_name),
+        }
+    }
+
+    fn url(&self, path: &str) -> error::Result<Url> {
+        Url::parse(&format!(
+            "https://{}/{}.onmicrosoft.com/{}/{path}",
+            self.host(),
+            self.tenant_name,
+            self.policy
+        ))
+        .map_err(|e| error::Error::Custom(e.into()))
+    }
+
+    /// Issuer of the tokens, in the default `https://<domain>/<tenant id>/v2.0/` format.
+    pub fn issuer(&self) -> String {
+        format!("https://{}/{}/v2.0/", self.host(), self.tenant_id)
+    }
+
+    pub fn auth_url(&self) -> error::Result<AuthUrl> {
+        Ok(AuthUrl::from_url(self.url("oauth2/v2.0/authorize")?))
+    }
+
+    pub fn token_url(&self) -> error::Result<TokenUrl> {
+        Ok(TokenUrl::from_url(self.url("oauth2/v2.0/token")?))
+    }
+
+    pub fn jwks_url(&self) -> error::Result<Url> {
+        self.url("discovery/v2.0/keys")
+    }
+}
+
+impl AzureB2c {
+    pub fn new(policy: &B2cPolicy) -> error::Result<Self> {
+        Ok(AzureB2c {
+            openid: OpenId::new(policy.jwks_url()?),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AzureB2cUser {
+    pub iss: String,
+    pub sub: String,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    /// Email addresses of the user, B2C sends them as a list.
+    #[serde(default)]
+    pub emails: Vec<String>,
+    /// Identity provider the user signed in with, for social accounts.
+    pub idp: Option<String>,
+    /// Policy that issued the token.
+    #[serde(alias = "acr")]
+    pub tfp: Option<String>,
+    /// Every other claim, including the `extension_` custom attributes.
+    #[serde(flatten)]
+    pub claims: Map<String, Value>,
+}
+
+impl AzureB2cUser {
+    /// Returns the custom attribute `name`, without its `extension_` prefix.
+    pub fn custom_attribute(&self, name: &str) -> Option<&Value> {
+        self.claims.get(&format!("extension_{name}"))
+    }
+}
+
+#[async_trait]
+impl SocioProvider for AzureB2c {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for AzureB2c {
+    type User = AzureB2cUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        self.openid
+            .exchange_code_as(client, code, pkce_verifier, nonce)
+            .await
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        self.openid
+            .refresh_as(client, refresh_token, scopes, id_token_subject)
+            .await
+    }
+}
+
+impl From<AzureB2cUser> for StandardUser {
+    fn from(value: AzureB2cUser) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.name,
+            email: value.emails.into_iter().next(),
+            picture: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AzureB2cConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+    #[serde(flatten)]
+    pub policy: B2cPolicy,
+}
+
+impl TryFrom<AzureB2cConfig> for SocioClient {
+    type Error = error::Error;
+
+    fn try_from(value: AzureB2cConfig) -> Result<Self, Self::Error> {
+        // B2C has neither a revocation nor a UserInfo endpoint for user flows, every claim is
+        // in the ID token.
+        let client_id = value.client_id.to_string();
+        Ok(SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            value.policy.auth_url()?,
+            value.policy.token_url()?,
+        )
+        // `offline_access` makes B2C issue refresh tokens, and the client ID as a scope an
+        // access token for the application itself.
+        .with_scopes([
+            "openid".to_string(),
+            "offline_access".to_string(),
+            client_id,
+        ])
+        .with_issuer(value.policy.issuer()))
+    }
+}
+
+impl TryFrom<AzureB2cConfig> for Socio<AzureB2c> {
+    type Error = error::Error;
+
+    fn try_from(value: AzureB2cConfig) -> Result<Self, Self::Error> {
+        let azure_b2c = AzureB2c::new(&value.policy)?;
+        Ok(Socio::new(value.try_into()?, azure_b2c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_urls() {
+        let policy = B2cPolicy {
+            tenant_name: "contoso".to_string(),
+            tenant_id: "775527ff-9a37-4307-8b3d-cc311f58d925".to_string(),
+            policy: "B2C_1_signupsignin".to_string(),
+            custom_domain: None,
+        };
+
+        assert_eq!(
+            policy.auth_url().unwrap().as_str(),
+            "https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_signupsignin/oauth2/v2.0/authorize"
+        );
+        assert_eq!(
+            policy.jwks_url().unwrap().as_str(),
+            "https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_signupsignin/discovery/v2.0/keys"
+        );
+        assert_eq!(
+            policy.issuer(),
+            "https://contoso.b2clogin.com/775527ff-9a37-4307-8b3d-cc311f58d925/v2.0/"
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        let client = SocioClient::try_from(AzureB2cConfig {
+            client_id: ClientId::new("90c0fe63-bcf2-44d5-8fb7-b8bbc0b29dc6".to_string()),
+            client_secret: ClientSecret::new("secret".to_string()),
+            redirect_url: RedirectUrl::new("https://app.example.com/callback".to_string()).unwrap(),
+            policy: B2cPolicy {
+                tenant_name: "contoso".to_string(),
+                tenant_id: "775527ff-9a37-4307-8b3d-cc311f58d925".to_string(),
+                policy: "B2C_1_signupsignin".to_string(),
+                custom_domain: None,
+            },
+        })
+        .unwrap();
+
+        let scopes = client
+            .scopes
+            .iter()
+            .map(|scope| scope.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            scopes,
+            [
+                "openid",
+                "offline_access",
+                "90c0fe63-bcf2-44d5-8fb7-b8bbc0b29dc6"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_attributes() {
+        let user = serde_json::from_str::<AzureB2cUser>(
+            r#"{
+                "iss": "https://contoso.b2clogin.com/775527ff-9a37-4307-8b3d-cc311f58d925/v2.0/",
+                "sub": "0b1f3c6e",
+                "emails": ["jdoe@example.com"],
+                "tfp": "B2C_1_signupsignin",
+                "extension_LoyaltyNumber": "12345"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            user.custom_attribute("LoyaltyNumber"),
+            Some(&Value::String("12345".to_string()))
+        );
+        assert_eq!(
+            StandardUser::from(user).email.as_deref(),
+            Some("jdoe@example.com")
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use socio::{
+    Socio, async_trait, error,
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, RevocationUrl, Scope, TokenUrl,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{IdTokenSubject, Nonce, Response, SocioClient},
+};
+use url::Url;
+
+use crate::openid::OpenId;
+
+/// An Amazon Cognito user pool, signing in through its hosted UI.
+#[derive(Clone, Debug)]
+pub struct Cognito {
+    pub openid: OpenId,
+}
+
+impl Cognito {
+    pub fn new(region: &str, user_pool_id: &str) -> error::Result<Self> {
+        Ok(Cognito {
+            openid: OpenId::new(parse_url(&format!(
+                "{}/.well-known/jwks.json",
+                issuer(region, user_pool_id)
+            ))?),
+        })
+    }
+}
+
+/// Issuer of the user pool tokens.
+pub fn issuer(region: &str, user_pool_id: &str) -> String {
+    format!("https://cognito-idp.{region}.amazonaws.com/{user_pool_id}")
+}
+
+fn parse_url(url: &str) -> error::Result<Url> {
+    Url::parse(url).map_err(|e| error::Error::Custom(e.into()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CognitoUser {
+    pub iss: String,
+    pub sub: String,
+    #[serde(rename = "cognito:username")]
+    pub username: Option<String>,
+    /// Groups of the user pool the user belongs to.
+    #[serde(
+        rename = "cognito:groups",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub groups: Vec<String>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub picture: Option<String>,
+    /// Every other claim, including the `custom:` attributes of the user pool.
+    #[serde(flatten)]
+    pub claims: Map<String, Value>,
+}
+
+impl CognitoUser {
+    /// Returns the custom attribute `name`, without its `custom:` prefix.
+    pub fn custom_attribute(&self, name: &str) -> Option<&Value> {
+        self.claims.get(&format!("custom:{name}"))
+    }
+}
+
+#[async_trait]
+impl SocioProvider for Cognito {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Cognito {
+    type User = CognitoUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        self.openid
+            .exchange_code_as(client, code, pkce_verifier, nonce)
+            .await
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        self.openid
+            .refresh_as(client, refresh_token, scopes, id_token_subject)
+            .await
+    }
+}
+
+impl From<CognitoUser> for StandardUser {
+    fn from(value: CognitoUser) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.name.or(value.username),
+            email: value.email,
+            picture: value.picture,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CognitoConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+    /// AWS region of the user pool, such as `eu-west-1`.
+    pub region: String,
+    /// Id of the user pool, such as `eu-west-1_AbCdEf123`.
+    pub user_pool_id: String,
+    /// Domain of the hosted UI, either `<prefix>.auth.<region>.amazoncognito.com` or a
+    /// custom domain.
+    pub domain: String,
+}
+
+impl TryFrom<CognitoConfig> for SocioClient {
+    type Error = error::Error;
+
+    fn try_from(value: CognitoConfig) -> Result<Self, Self::Error> {
+        let endpoint = |path| parse_url(&format!("https://{}/oauth2/{path}", value.domain));
+
+        Ok(SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(endpoint("authorize")?),
+            TokenUrl::from_url(endpoint("token")?),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_issuer(issuer(&value.region, &value.user_pool_id))
+        .with_revocation_endpoint(RevocationUrl::from_url(endpoint("revoke")?))
+        .with_userinfo_endpoint(endpoint("userInfo")?))
+    }
+}
+
+impl TryFrom<CognitoConfig> for Socio<Cognito> {
+    type Error = error::Error;
+
+    fn try_from(value: CognitoConfig) -> Result<Self, Self::Error> {
+        let cognito = Cognito::new(&value.region, &value.user_pool_id)?;
+        Ok(Socio::new(value.try_into()?, cognito))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_pool_urls() {
+        let config = CognitoConfig {
+            client_id: ClientId::new("app".to_string()),
+            client_secret: ClientSecret::new("secret".to_string()),
+            redirect_url: RedirectUrl::new("https://app.example.com/callback".to_string()).unwrap(),
+            region: "eu-west-1".to_string(),
+            user_pool_id: "eu-west-1_AbCdEf123".to_string(),
+            domain: "acme.auth.eu-west-1.amazoncognito.com".to_string(),
+        };
+
+        let cognito = Cognito::new(&config.region, &config.user_pool_id).unwrap();
+        assert_eq!(
+            cognito.openid.jwks_url.as_str(),
+            "https://cognito-idp.eu-west-1.amazonaws.com/eu-west-1_AbCdEf123/.well-known/jwks.json"
+        );
+
+        let client = SocioClient::try_from(config).unwrap();
+        assert_eq!(
+            client.issuer.as_deref(),
+            Some("https://cognito-idp.eu-west-1.amazonaws.com/eu-west-1_AbCdEf123")
+        );
+        assert_eq!(
+            client.authorize_endpoint.as_str(),
+            "https://acme.auth.eu-west-1.amazoncognito.com/oauth2/authorize"
+        );
+    }
+
+    #[test]
+    fn test_groups_and_custom_attributes() {
+        let user = serde_json::from_str::<CognitoUser>(
+            r#"{
+                "iss": "https://cognito-idp.eu-west-1.amazonaws.com/eu-west-1_AbCdEf123",
+                "sub": "7d8ca528",
+                "cognito:username": "jdoe",
+                "cognito:groups": ["admins"],
+                "custom:plan": "pro"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(user.groups, ["admins"]);
+        assert_eq!(
+            user.custom_attribute("plan"),
+            Some(&Value::String("pro".to_string()))
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, EmptyExtraTokenFields, RedirectUrl, RevocationUrl,
+        TokenResponse, TokenUrl,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{Nonce, Response, SocioClient},
+};
+use url_macro::url;
+
+pub const USER_URL: &str = "https://discord.com/api/v10/users/@me";
+pub const CDN_URL: &str = "https://cdn.discordapp.com";
+
+#[derive(Clone, Debug)]
+pub struct Discord;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscordUser {
+    pub id: String,
+    pub username: String,
+    /// Four digit tag of legacy usernames, `"0"` for users on the unique username system.
+    pub discriminator: String,
+    pub global_name: Option<String>,
+    /// Hash of the avatar, see [`DiscordUser::avatar_url`].
+    pub avatar: Option<String>,
+    pub email: Option<String>,
+    pub verified: Option<bool>,
+}
+
+impl DiscordUser {
+    /// URL of the avatar, or of the default avatar when the user has not set one.
+    pub fn avatar_url(&self) -> String {
+        match &self.avatar {
+            Some(hash) => {
+                let extension = if hash.starts_with("a_") { "gif" } else { "png" };
+                format!("{CDN_URL}/avatars/{}/{hash}.{extension}", self.id)
+            }
+            None => {
+                let index = if self.discriminator == "0" {
+                    self.id.parse::<u64>().map(|id| (id >> 22) % 6)
+                } else {
+                    self.discriminator.parse::<u64>().map(|d| d % 5)
+                };
+                format!("{CDN_URL}/embed/avatars/{}.png", index.unwrap_or(0))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SocioProvider for Discord {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Discord {
+    type User = DiscordUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        _nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        let response = client
+            .exchange_code::<EmptyExtraTokenFields>(code, pkce_verifier)
+            .await?;
+
+        let user = client
+            .http_client
+            .get_json::<DiscordUser>(USER_URL, response.access_token(), &[])
+            .await?;
+
+        Ok(Response::from_standard_token_response(&response, user))
+    }
+}
+
+impl From<DiscordUser> for StandardUser {
+    fn from(value: DiscordUser) -> Self {
+        let picture = Some(value.avatar_url());
+
+        StandardUser {
+            id: value.id,
+            name: value.global_name.or(Some(value.username)),
+            email: value.email,
+            picture,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiscordConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+}
+
+impl From<DiscordConfig> for SocioClient {
+    fn from(value: DiscordConfig) -> Self {
+        let auth_url = url!("https://discord.com/oauth2/authorize");
+        let token_url = url!("https://discord.com/api/oauth2/token");
+
+        SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(auth_url),
+            TokenUrl::from_url(token_url),
+        )
+        .with_scopes(["identify", "email"])
+        .with_revocation_endpoint(RevocationUrl::from_url(url!(
+            "https://discord.com/api/oauth2/token/revoke"
+        )))
+    }
+}
+
+impl From<DiscordConfig> for Socio<Discord> {
+    fn from(value: DiscordConfig) -> Self {
+        Socio::new(value.into(), Discord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(discriminator: &str, avatar: Option<&str>) -> DiscordUser {
+        DiscordUser {
+            id: "80351110224678912".to_string(),
+            username: "nelly".to_string(),
+            discriminator: discriminator.to_string(),
+            global_name: None,
+            avatar: avatar.map(str::to_string),
+            email: None,
+            verified: None,
+        }
+    }
+
+    #[test]
+    fn test_avatar_url() {
+        assert_eq!(
+            user("0", Some("8342729096ea3675442027381ff50dfe")).avatar_url(),
+            "https://cdn.discordapp.com/avatars/80351110224678912/8342729096ea3675442027381ff50dfe.png"
+        );
+        assert_eq!(
+            user("0", Some("a_8342729096ea3675442027381ff50dfe")).avatar_url(),
+            "https://cdn.discordapp.com/avatars/80351110224678912/a_8342729096ea3675442027381ff50dfe.gif"
+        );
+        assert_eq!(
+            user("1337", None).avatar_url(),
+            "https://cdn.discordapp.com/embed/avatars/2.png"
+        );
+        assert_eq!(
+            user("0", None).avatar_url(),
+            "https://cdn.discordapp.com/embed/avatars/5.png"
+        );
+    }
+}
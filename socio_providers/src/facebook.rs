@@ -1,13 +1,19 @@
 use serde::{Deserialize, Serialize};
 use socio::{
     Socio, async_trait, error,
-    jwt::verify_jwt_with_jwks_endpoint,
-    oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, Scope, TokenUrl},
+    jwt::{IdTokenValidator, JwksCache},
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, Scope, TokenResponse, TokenUrl,
+    },
     providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
-    types::{OpenIdTokenField, Response, SocioClient},
+    types::{
+        IdTokenSubject, Nonce, OpenIdTokenField, OptionalOpenIdTokenField, Response, SocioClient,
+    },
 };
 use url_macro::url;
 
+pub const JWKS_URL: &str = "https://www.facebook.com/.well-known/oauth/openid/jwks";
+
 #[derive(Clone, Debug)]
 pub struct Facebook;
 
@@ -30,12 +36,26 @@ impl SocioProvider for Facebook {
         client: &SocioClient,
         code: socio::oauth2::AuthorizationCode,
         pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<StandardUser>> {
         Ok(self
-            .exchange_code_for_user(client, code, pkce_verifier)
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
             .await?
             .standardize())
     }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
 }
 
 #[async_trait]
@@ -47,22 +67,59 @@ impl UserAwareSocioProvider for Facebook {
         client: &SocioClient,
         code: socio::oauth2::AuthorizationCode,
         pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<Self::User>> {
         let response = client
             .exchange_code::<OpenIdTokenField>(code, pkce_verifier)
             .await?;
 
-        let token = verify_jwt_with_jwks_endpoint::<FacebookUser>(
-            &response.extra_fields().id_token,
-            "https://www.facebook.com/.well-known/oauth/openid/jwks",
-            &client.client_id,
+        let token = IdTokenValidator::for_client(client)
+            .nonce(nonce)
+            .access_token(response.access_token())
+            .validate::<FacebookUser>(
+                &response.extra_fields().id_token,
+                &JwksCache::shared(JWKS_URL),
+            )
+            .await?;
+
+        let id_token_subject = IdTokenSubject::new(&token.claims.iss, &token.claims.sub);
+
+        Ok(
+            Response::from_standard_token_response(&response, token.claims)
+                .with_id_token_subject(Some(id_token_subject)),
         )
-        .await?;
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        let response = client
+            .refresh::<OptionalOpenIdTokenField>(refresh_token, scopes)
+            .await?;
+
+        let user = match &response.extra_fields().id_token {
+            Some(id_token) => Some(
+                IdTokenValidator::for_client(client)
+                    .subject(id_token_subject)
+                    .access_token(response.access_token())
+                    .validate::<FacebookUser>(id_token, &JwksCache::shared(JWKS_URL))
+                    .await?
+                    .claims,
+            ),
+            None => None,
+        };
+
+        let id_token_subject = user
+            .as_ref()
+            .map(|user| IdTokenSubject::new(&user.iss, &user.sub))
+            .or_else(|| id_token_subject.cloned());
 
-        Ok(Response::from_standard_token_response(
-            &response,
-            token.claims,
-        ))
+        Ok(Response::from_standard_token_response(&response, user)
+            .with_id_token_subject(id_token_subject))
     }
 }
 
@@ -89,17 +146,16 @@ impl From<FacebookConfig> for SocioClient {
         let auth_url = url!("https://www.facebook.com/v22.0/dialog/oauth");
         let token_url = url!("https://graph.facebook.com/v22.0/oauth/access_token");
 
-        SocioClient {
-            client_id: value.client_id,
-            client_secret: value.client_secret,
-            redirect_uri: value.redirect_url,
-            authorize_endpoint: AuthUrl::from_url(auth_url),
-            token_endpoint: TokenUrl::from_url(token_url),
-            scopes: ["openid", "profile", "email"]
-                .iter()
-                .map(|s| Scope::new(s.to_string()))
-                .collect(),
-        }
+        // Facebook revokes permissions through the Graph API instead.
+        SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(auth_url),
+            TokenUrl::from_url(token_url),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_issuer("https://www.facebook.com")
     }
 }
 
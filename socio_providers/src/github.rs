@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    oauth2::{
+        AccessToken, AuthUrl, ClientId, ClientSecret, EmptyExtraTokenFields, RedirectUrl,
+        TokenResponse, TokenUrl,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{Nonce, Response, SocioClient},
+};
+use url::Url;
+use url_macro::url;
+
+/// GitHub, through its REST API since it is not an OpenID provider.
+#[derive(Clone, Debug)]
+pub struct Github {
+    /// Base URL of the REST API, `https://api.github.com` or `<base url>/api/v3` for GitHub
+    /// Enterprise Server.
+    pub api_url: Url,
+}
+
+impl Default for Github {
+    fn default() -> Self {
+        Github {
+            api_url: url!("https://api.github.com"),
+        }
+    }
+}
+
+impl Github {
+    /// Provider for a GitHub Enterprise Server instance, such as `https://github.example.com`.
+    pub fn enterprise(base_url: &Url) -> Self {
+        Github {
+            api_url: join(base_url, "api/v3"),
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        client: &SocioClient,
+        path: &str,
+        access_token: &AccessToken,
+    ) -> error::Result<T> {
+        client
+            .http_client
+            .get_json(
+                join(&self.api_url, path).as_str(),
+                access_token,
+                &[
+                    ("accept", "application/vnd.github+json"),
+                    ("user-agent", "socio"),
+                    ("x-github-api-version", "2022-11-28"),
+                ],
+            )
+            .await
+    }
+
+    async fn fetch_user(
+        &self,
+        client: &SocioClient,
+        access_token: &AccessToken,
+    ) -> error::Result<GithubUser> {
+        let mut user = self.get::<GithubUser>(client, "user", access_token).await?;
+
+        // Listing emails requires the `user:email` scope, without it only the public email
+        // is known.
+        match self
+            .get::<Vec<GithubEmail>>(client, "user/emails", access_token)
+            .await
+        {
+            Ok(emails) => {
+                if let Some(email) = primary_verified_email(emails) {
+                    user.email = Some(email);
+                }
+            }
+            Err(error::Error::UnexpectedStatus(status)) if status.is_client_error() => {}
+            Err(e) => return Err(e),
+        }
+
+        Ok(user)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GithubUser {
+    pub id: u64,
+    pub login: String,
+    pub name: Option<String>,
+    /// The primary email when it is verified, otherwise the public email of the profile.
+    pub email: Option<String>,
+    pub avatar_url: Option<String>,
+    pub html_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GithubEmail {
+    pub email: String,
+    pub primary: bool,
+    pub verified: bool,
+}
+
+fn primary_verified_email(emails: Vec<GithubEmail>) -> Option<String> {
+    emails
+        .into_iter()
+        .find(|email| email.primary && email.verified)
+        .map(|email| email.email)
+}
+
+fn join(base: &Url, path: &str) -> Url {
+    let mut url = base.clone();
+    url.path_segments_mut()
+        .expect("GitHub URLs have a path")
+        .pop_if_empty()
+        .extend(path.split('/'));
+    url
+}
+
+#[async_trait]
+impl SocioProvider for Github {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Github {
+    type User = GithubUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        _nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        let response = client
+            .exchange_code::<EmptyExtraTokenFields>(code, pkce_verifier)
+            .await?;
+
+        let user = self.fetch_user(client, response.access_token()).await?;
+
+        Ok(Response::from_standard_token_response(&response, user))
+    }
+}
+
+impl From<GithubUser> for StandardUser {
+    fn from(value: GithubUser) -> Self {
+        StandardUser {
+            id: value.id.to_string(),
+            name: value.name.or(Some(value.login)),
+            email: value.email,
+            picture: value.avatar_url,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GithubConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+    /// Base URL of a GitHub Enterprise Server instance, github.com when unset.
+    #[serde(default)]
+    pub base_url: Option<Url>,
+}
+
+impl From<GithubConfig> for SocioClient {
+    fn from(value: GithubConfig) -> Self {
+        let base_url = value.base_url.unwrap_or(url!("https://github.com"));
+
+        // Tokens are revoked through the REST API with basic authentication instead.
+        SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(join(&base_url, "login/oauth/authorize")),
+            TokenUrl::from_url(join(&base_url, "login/oauth/access_token")),
+        )
+        .with_scopes(["read:user", "user:email"])
+    }
+}
+
+impl From<GithubConfig> for Socio<Github> {
+    fn from(value: GithubConfig) -> Self {
+        let github = match &value.base_url {
+            Some(base_url) => Github::enterprise(base_url),
+            None => Github::default(),
+        };
+        Socio::new(value.into(), github)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use socio::http_client::{HttpClient, HttpError, HttpRequest, HttpResponse, SharedHttpClient};
+
+    use super::*;
+
+    /// Serves the user endpoints of the REST API.
+    struct GithubApi {
+        emails: &'static str,
+    }
+
+    #[async_trait]
+    impl HttpClient for GithubApi {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+            let body = match request.uri().path() {
+                "/user" => {
+                    r#"{"id": 1, "login": "octocat", "name": null, "email": "public@example.com"}"#
+                }
+                "/user/emails" => self.emails,
+                path => panic!("unexpected request to {path}"),
+            };
+            Ok(HttpResponse::new(body.as_bytes().to_vec()))
+        }
+    }
+
+    async fn fetch_user(emails: &'static str) -> GithubUser {
+        let client = SocioClient::from(GithubConfig {
+            client_id: ClientId::new("client".to_string()),
+            client_secret: ClientSecret::new("secret".to_string()),
+            redirect_url: RedirectUrl::new("https://example.com/callback".to_string()).unwrap(),
+            base_url: None,
+        })
+        .with_http_client(SharedHttpClient::new(GithubApi { emails }));
+
+        Github::default()
+            .fetch_user(&client, &AccessToken::new("token".to_string()))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_user_email() {
+        let user =
+            fetch_user(r#"[{"email": "primary@example.com", "primary": true, "verified": true}]"#)
+                .await;
+        assert_eq!(user.email.as_deref(), Some("primary@example.com"));
+
+        // The public email is kept when the primary one is not verified.
+        let user =
+            fetch_user(r#"[{"email": "primary@example.com", "primary": true, "verified": false}]"#)
+                .await;
+        assert_eq!(user.email.as_deref(), Some("public@example.com"));
+    }
+
+    #[test]
+    fn test_primary_verified_email() {
+        let emails = serde_json::from_str::<Vec<GithubEmail>>(
+            r#"[
+                {"email": "old@example.com", "primary": false, "verified": true},
+                {"email": "new@example.com", "primary": true, "verified": true}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            primary_verified_email(emails).as_deref(),
+            Some("new@example.com")
+        );
+
+        let unverified = vec![GithubEmail {
+            email: "new@example.com".to_string(),
+            primary: true,
+            verified: false,
+        }];
+        assert_eq!(primary_verified_email(unverified), None);
+    }
+
+    #[test]
+    fn test_enterprise_urls() {
+        let base_url = url!("https://github.example.com/");
+        assert_eq!(
+            Github::enterprise(&base_url).api_url.as_str(),
+            "https://github.example.com/api/v3"
+        );
+        assert_eq!(
+            join(&base_url, "login/oauth/authorize").as_str(),
+            "https://github.example.com/login/oauth/authorize"
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    jwt::{IdTokenValidator, JwksCache},
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, RevocationUrl, Scope,
+        StandardTokenResponse, TokenResponse, TokenUrl, basic::BasicTokenType,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{IdTokenSubject, Nonce, OptionalOpenIdTokenField, Response, SocioClient},
+};
+use url::Url;
+use url_macro::url;
+
+/// GitLab, either gitlab.com or a self-managed instance.
+#[derive(Clone, Debug)]
+pub struct Gitlab {
+    pub base_url: Url,
+}
+
+impl Default for Gitlab {
+    fn default() -> Self {
+        Gitlab {
+            base_url: url!("https://gitlab.com"),
+        }
+    }
+}
+
+impl Gitlab {
+    pub fn new(base_url: Url) -> Self {
+        Gitlab { base_url }
+    }
+
+    /// Issuer of the ID tokens, the instance URL without a trailing slash.
+    pub fn issuer(&self) -> String {
+        self.base_url.as_str().trim_end_matches('/').to_string()
+    }
+
+    pub fn url(&self, path: &str) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("GitLab URLs have a path")
+            .pop_if_empty()
+            .extend(path.split('/'));
+        url
+    }
+
+    pub fn jwks(&self) -> std::sync::Arc<JwksCache> {
+        JwksCache::shared(self.url("oauth/discovery/keys").as_str())
+    }
+
+    /// Builds the response with the user from the ID token when the `openid` scope was
+    /// granted, and from the REST API otherwise.
+    async fn user_response(
+        &self,
+        client: &SocioClient,
+        response: &StandardTokenResponse<OptionalOpenIdTokenField, BasicTokenType>,
+        nonce: Option<&Nonce>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<GitlabUser>> {
+        if !openid_granted(client, response) {
+            let user = client
+                .http_client
+                .get_json::<GitlabApiUser>(
+                    self.url("api/v4/user").as_str(),
+                    response.access_token(),
+                    &[],
+                )
+                .await?;
+            return Ok(Response::from_standard_token_response(
+                response,
+                user.into(),
+            ));
+        }
+
+        let id_token = response
+            .extra_fields()
+            .id_token
+            .as_ref()
+            .ok_or_else(|| error::Error::InvalidIdToken("missing ID token".to_string()))?;
+
+        let token = IdTokenValidator::for_client(client)
+            .issuer(self.issuer())
+            .nonce(nonce)
+            .subject(id_token_subject)
+            .access_token(response.access_token())
+            .validate::<GitlabUser>(id_token, &self.jwks())
+            .await?;
+
+        let id_token_subject = IdTokenSubject::new(self.issuer(), &token.claims.sub);
+        // The groups are only part of the UserInfo response.
+        let user = client
+            .merge_userinfo(response.access_token(), token.claims)
+            .await?;
+
+        Ok(Response::from_standard_token_response(response, user)
+            .with_id_token_subject(Some(id_token_subject)))
+    }
+}
+
+fn openid_granted(
+    client: &SocioClient,
+    response: &StandardTokenResponse<OptionalOpenIdTokenField, BasicTokenType>,
+) -> bool {
+    // The scope is only returned when it differs from the requested one.
+    match response.scopes() {
+        Some(scopes) => scopes.iter().any(|scope| scope.as_str() == "openid"),
+        None => client.is_openid(),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitlabUser {
+    pub sub: String,
+    pub name: Option<String>,
+    /// Username of the user, `preferred_username` in the ID token.
+    #[serde(rename = "preferred_username")]
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub picture: Option<String>,
+    pub profile: Option<String>,
+    /// Full paths of the groups the user is a member of, from the UserInfo response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    /// Full paths of the groups the user is a direct member of, from the ID token.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups_direct: Vec<String>,
+}
+
+/// User returned by the `/api/v4/user` endpoint.
+#[derive(Deserialize)]
+struct GitlabApiUser {
+    id: u64,
+    username: String,
+    name: Option<String>,
+    email: Option<String>,
+    avatar_url: Option<String>,
+    web_url: Option<String>,
+}
+
+impl From<GitlabApiUser> for GitlabUser {
+    fn from(value: GitlabApiUser) -> Self {
+        GitlabUser {
+            sub: value.id.to_string(),
+            name: value.name,
+            username: Some(value.username),
+            email: value.email,
+            email_verified: None,
+            picture: value.avatar_url,
+            profile: value.web_url,
+            groups: Vec::new(),
+            groups_direct: Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl SocioProvider for Gitlab {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Gitlab {
+    type User = GitlabUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        let response = client
+            .exchange_code::<OptionalOpenIdTokenField>(code, pkce_verifier)
+            .await?;
+
+        self.user_response(client, &response, nonce, None).await
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        let response = client
+            .refresh::<OptionalOpenIdTokenField>(refresh_token, scopes)
+            .await?;
+
+        // Refresh responses may leave the ID token out.
+        if openid_granted(client, &response) && response.extra_fields().id_token.is_none() {
+            return Ok(Response::from_standard_token_response(&response, None)
+                .with_id_token_subject(id_token_subject.cloned()));
+        }
+
+        Ok(self
+            .user_response(client, &response, None, id_token_subject)
+            .await?
+            .map_user(Some))
+    }
+}
+
+impl From<GitlabUser> for StandardUser {
+    fn from(value: GitlabUser) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.name.or(value.username),
+            email: value.email,
+            picture: value.picture,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GitlabConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+    /// Base URL of a self-managed instance, gitlab.com when unset.
+    #[serde(default)]
+    pub base_url: Option<Url>,
+    /// Scopes to request, `openid`, `profile` and `email` when unset. Without `openid` the
+    /// user is read from the REST API, which requires the `read_user` scope.
+    #[serde(default)]
+    pub scopes: Option<Vec<Scope>>,
+}
+
+impl GitlabConfig {
+    fn provider(&self) -> Gitlab {
+        match &self.base_url {
+            Some(base_url) => Gitlab::new(base_url.clone()),
+            None => Gitlab::default(),
+        }
+    }
+}
+
+impl From<GitlabConfig> for SocioClient {
+    fn from(value: GitlabConfig) -> Self {
+        let gitlab = value.provider();
+        let mut client = SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(gitlab.url("oauth/authorize")),
+            TokenUrl::from_url(gitlab.url("oauth/token")),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_issuer(gitlab.issuer())
+        .with_revocation_endpoint(RevocationUrl::from_url(gitlab.url("oauth/revoke")))
+        .with_userinfo_endpoint(gitlab.url("oauth/userinfo"));
+
+        if let Some(scopes) = value.scopes {
+            client.scopes = scopes;
+        }
+        client
+    }
+}
+
+impl From<GitlabConfig> for Socio<Gitlab> {
+    fn from(value: GitlabConfig) -> Self {
+        let gitlab = value.provider();
+        Socio::new(value.into(), gitlab)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use socio::http_client::{HttpClient, HttpError, HttpRequest, HttpResponse, SharedHttpClient};
+
+    use super::*;
+
+    /// Token endpoint answering refreshes without an ID token.
+    struct TokenEndpoint;
+
+    #[async_trait]
+    impl HttpClient for TokenEndpoint {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+            assert_eq!(request.uri().path(), "/oauth/token");
+            let mut response = HttpResponse::new(
+                br#"{"access_token": "access", "token_type": "Bearer", "scope": "openid"}"#
+                    .to_vec(),
+            );
+            response
+                .headers_mut()
+                .insert("content-type", "application/json".parse().unwrap());
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn test_self_managed_urls() {
+        let gitlab = Gitlab::new(url!("https://gitlab.example.com/gitlab/"));
+
+        assert_eq!(gitlab.issuer(), "https://gitlab.example.com/gitlab");
+        assert_eq!(
+            gitlab.url("api/v4/user").as_str(),
+            "https://gitlab.example.com/gitlab/api/v4/user"
+        );
+        assert_eq!(
+            Gitlab::default().url("oauth/authorize").as_str(),
+            "https://gitlab.com/oauth/authorize"
+        );
+    }
+
+    #[test]
+    fn test_api_user_conversion() {
+        let user = serde_json::from_str::<GitlabApiUser>(
+            r#"{"id": 42, "username": "jdoe", "name": null, "avatar_url": null}"#,
+        )
+        .unwrap();
+
+        let user = StandardUser::from(GitlabUser::from(user));
+        assert_eq!(user.id, "42");
+        assert_eq!(user.name.as_deref(), Some("jdoe"));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_without_id_token() {
+        let socio = Socio::<Gitlab>::from(GitlabConfig {
+            client_id: ClientId::new("client".to_string()),
+            client_secret: ClientSecret::new("secret".to_string()),
+            redirect_url: RedirectUrl::new("https://example.com/callback".to_string()).unwrap(),
+            base_url: None,
+            scopes: None,
+        })
+        .with_http_client(SharedHttpClient::new(TokenEndpoint));
+
+        let subject = IdTokenSubject::new("https://gitlab.com", "42");
+        let response = socio
+            .refresh_token_for_user(
+                &RefreshToken::new("refresh".to_string()),
+                None,
+                Some(&subject),
+            )
+            .await
+            .unwrap();
+        assert!(response.user.is_none());
+        assert_eq!(response.refresh_token.unwrap().secret(), "refresh");
+        assert_eq!(response.id_token_subject, Some(subject));
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, RevocationUrl, Scope, TokenUrl,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{IdTokenSubject, Nonce, Response, SocioClient},
+};
+use url::Url;
+
+use crate::openid::OpenId;
+
+/// A Keycloak realm.
+#[derive(Clone, Debug)]
+pub struct Keycloak {
+    pub openid: OpenId,
+}
+
+impl Keycloak {
+    pub fn new(base_url: &Url, realm: &str) -> Self {
+        Keycloak {
+            openid: OpenId::new(endpoint(base_url, realm, "certs")),
+        }
+    }
+}
+
+/// URL of the realm, which is also the issuer of its tokens. `base_url` must include the
+/// `/auth` prefix on Keycloak versions older than 17.
+pub fn realm_url(base_url: &Url, realm: &str) -> Url {
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .expect("Keycloak URLs have a path")
+        .pop_if_empty()
+        .extend(["realms", realm]);
+    url
+}
+
+fn endpoint(base_url: &Url, realm: &str, name: &str) -> Url {
+    let mut url = realm_url(base_url, realm);
+    url.path_segments_mut()
+        .expect("Keycloak URLs have a path")
+        .extend(["protocol", "openid-connect", name]);
+    url
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeycloakUser {
+    pub iss: String,
+    pub sub: String,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    /// Realm roles, only present when the realm roles mapper adds them to the ID token or
+    /// the UserInfo response.
+    pub realm_access: Option<KeycloakAccess>,
+    /// Client roles by client id, with the same requirement as `realm_access`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub resource_access: HashMap<String, KeycloakAccess>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct KeycloakAccess {
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl KeycloakUser {
+    pub fn realm_roles(&self) -> &[String] {
+        self.realm_access
+            .as_ref()
+            .map_or(&[], |access| access.roles.as_slice())
+    }
+
+    pub fn client_roles(&self, client_id: &str) -> &[String] {
+        self.resource_access
+            .get(client_id)
+            .map_or(&[], |access| access.roles.as_slice())
+    }
+}
+
+#[async_trait]
+impl SocioProvider for Keycloak {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Keycloak {
+    type User = KeycloakUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        self.openid
+            .exchange_code_as(client, code, pkce_verifier, nonce)
+            .await
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        self.openid
+            .refresh_as(client, refresh_token, scopes, id_token_subject)
+            .await
+    }
+}
+
+impl From<KeycloakUser> for StandardUser {
+    fn from(value: KeycloakUser) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.name.or(value.preferred_username),
+            email: value.email,
+            picture: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeycloakConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+    /// URL of the Keycloak server, such as `https://sso.example.com`.
+    pub base_url: Url,
+    pub realm: String,
+}
+
+impl From<KeycloakConfig> for SocioClient {
+    fn from(value: KeycloakConfig) -> Self {
+        let endpoint = |name| endpoint(&value.base_url, &value.realm, name);
+
+        SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(endpoint("auth")),
+            TokenUrl::from_url(endpoint("token")),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_issuer(realm_url(&value.base_url, &value.realm))
+        .with_revocation_endpoint(RevocationUrl::from_url(endpoint("revoke")))
+        .with_userinfo_endpoint(endpoint("userinfo"))
+    }
+}
+
+impl From<KeycloakConfig> for Socio<Keycloak> {
+    fn from(value: KeycloakConfig) -> Self {
+        let keycloak = Keycloak::new(&value.base_url, &value.realm);
+        Socio::new(value.into(), keycloak)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url_macro::url;
+
+    use super::*;
+
+    #[test]
+    fn test_realm_endpoints() {
+        let config = KeycloakConfig {
+            client_id: ClientId::new("app".to_string()),
+            client_secret: ClientSecret::new("secret".to_string()),
+            redirect_url: RedirectUrl::new("https://app.example.com/callback".to_string()).unwrap(),
+            base_url: url!("https://sso.example.com/"),
+            realm: "acme".to_string(),
+        };
+
+        let keycloak = Keycloak::new(&config.base_url, &config.realm);
+        assert_eq!(
+            keycloak.openid.jwks_url.as_str(),
+            "https://sso.example.com/realms/acme/protocol/openid-connect/certs"
+        );
+
+        let client = SocioClient::from(config);
+        assert_eq!(
+            client.issuer.as_deref(),
+            Some("https://sso.example.com/realms/acme")
+        );
+        assert_eq!(
+            client.token_endpoint.as_str(),
+            "https://sso.example.com/realms/acme/protocol/openid-connect/token"
+        );
+    }
+
+    #[test]
+    fn test_roles() {
+        let user = serde_json::from_str::<KeycloakUser>(
+            r#"{
+                "iss": "https://sso.example.com/realms/acme",
+                "sub": "f1b5c0e4",
+                "realm_access": { "roles": ["admin", "offline_access"] },
+                "resource_access": { "app": { "roles": ["editor"] } }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(user.realm_roles(), ["admin", "offline_access"]);
+        assert_eq!(user.client_roles("app"), ["editor"]);
+        assert!(user.client_roles("other").is_empty());
+    }
+}
@@ -1,4 +1,19 @@
+pub mod apple;
+pub mod auth0;
+pub mod azure_b2c;
+pub mod cognito;
+pub mod discord;
 pub mod facebook;
+pub mod github;
+pub mod gitlab;
 pub mod google;
+pub mod keycloak;
+pub mod linkedin;
 pub mod microsoft;
+pub mod okta;
 pub mod openid;
+pub mod slack;
+pub mod steam;
+pub mod telegram;
+pub mod twitch;
+pub mod x;
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    jwt::{IdTokenValidator, JwksCache},
+    oauth2::{AuthType, AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenResponse, TokenUrl},
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{Nonce, OpenIdTokenField, Response, SocioClient},
+};
+use url_macro::url;
+
+pub const JWKS_URL: &str = "https://www.linkedin.com/oauth/openid/jwks";
+
+/// Sign In with LinkedIn using OpenID Connect.
+#[derive(Clone, Debug)]
+pub struct Linkedin;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkedinUser {
+    pub iss: String,
+    pub aud: String,
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+}
+
+#[async_trait]
+impl SocioProvider for Linkedin {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Linkedin {
+    type User = LinkedinUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        let response = client
+            .exchange_code::<OpenIdTokenField>(code, pkce_verifier)
+            .await?;
+
+        let token = IdTokenValidator::for_client(client)
+            .nonce(nonce)
+            .access_token(response.access_token())
+            .validate::<LinkedinUser>(
+                &response.extra_fields().id_token,
+                &JwksCache::shared(JWKS_URL),
+            )
+            .await?;
+
+        // The ID token may leave out profile claims the UserInfo endpoint returns.
+        let user = client
+            .merge_userinfo(response.access_token(), token.claims)
+            .await?;
+
+        Ok(Response::from_standard_token_response(&response, user))
+    }
+}
+
+impl From<LinkedinUser> for StandardUser {
+    fn from(value: LinkedinUser) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.name,
+            email: value.email,
+            picture: value.picture,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkedinConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+}
+
+impl From<LinkedinConfig> for SocioClient {
+    fn from(value: LinkedinConfig) -> Self {
+        let auth_url = url!("https://www.linkedin.com/oauth/v2/authorization");
+        let token_url = url!("https://www.linkedin.com/oauth/v2/accessToken");
+
+        SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(auth_url),
+            TokenUrl::from_url(token_url),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_issuer("https://www.linkedin.com/oauth")
+        .with_userinfo_endpoint(url!("https://api.linkedin.com/v2/userinfo"))
+        // LinkedIn only reads the client credentials from the request body.
+        .with_auth_type(AuthType::RequestBody)
+    }
+}
+
+impl From<LinkedinConfig> for Socio<Linkedin> {
+    fn from(value: LinkedinConfig) -> Self {
+        Socio::new(value.into(), Linkedin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use socio::userinfo::merge_claims;
+
+    use super::*;
+
+    #[test]
+    fn test_user_with_userinfo() {
+        let claims = serde_json::from_str::<LinkedinUser>(
+            r#"{
+                "iss": "https://www.linkedin.com/oauth",
+                "aud": "app",
+                "sub": "782bbtaQ",
+                "name": "John Doe",
+                "email": "doe@email.com",
+                "email_verified": true
+            }"#,
+        )
+        .unwrap();
+        let userinfo = serde_json::from_str(
+            r#"{
+                "sub": "782bbtaQ",
+                "name": "John Doe",
+                "given_name": "John",
+                "family_name": "Doe",
+                "picture": "https://media.licdn-ei.com/dms/image/C5F03AQHqK8v7tB1HCQ/profile.jpg",
+                "email": "doe@email.com",
+                "email_verified": true
+            }"#,
+        )
+        .unwrap();
+
+        let user = merge_claims(claims, userinfo).unwrap();
+        assert_eq!(user.given_name.as_deref(), Some("John"));
+
+        let user = StandardUser::from(user);
+        assert_eq!(user.id, "782bbtaQ");
+        assert_eq!(user.name.as_deref(), Some("John Doe"));
+        assert_eq!(user.email.as_deref(), Some("doe@email.com"));
+        assert_eq!(
+            user.picture.as_deref(),
+            Some("https://media.licdn-ei.com/dms/image/C5F03AQHqK8v7tB1HCQ/profile.jpg")
+        );
+    }
+}
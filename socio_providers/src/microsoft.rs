@@ -1,10 +1,15 @@
 use serde::{Deserialize, Serialize};
 use socio::{
     Socio, async_trait, error,
-    jwt::verify_jwt_with_jwks_endpoint,
-    oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, Scope, TokenUrl},
+    jwt::{IdTokenValidator, JwksCache},
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, Scope, TokenResponse, TokenUrl,
+    },
     providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
-    types::{OpenIdTokenField, Response, SocioClient},
+    types::{
+        IdTokenSubject, Nonce, OpenIdTokenField, OptionalOpenIdTokenField, Response, ResponseMode,
+        SocioClient,
+    },
 };
 
 #[derive(Clone, Debug)]
@@ -19,6 +24,12 @@ pub struct MicrosoftUser {
     pub preferred_username: String,
     #[serde(default)]
     pub email: Option<String>,
+    /// Id of the tenant that issued the token.
+    #[serde(default)]
+    pub tid: Option<String>,
+    /// Only available from the UserInfo endpoint.
+    #[serde(default)]
+    pub picture: Option<String>,
 }
 
 #[async_trait]
@@ -28,12 +39,26 @@ impl SocioProvider for Microsoft {
         client: &SocioClient,
         code: socio::oauth2::AuthorizationCode,
         pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<StandardUser>> {
         Ok(self
-            .exchange_code_for_user(client, code, pkce_verifier)
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
             .await?
             .standardize())
     }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
 }
 
 #[async_trait]
@@ -45,23 +70,77 @@ impl UserAwareSocioProvider for Microsoft {
         client: &SocioClient,
         code: socio::oauth2::AuthorizationCode,
         pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<Self::User>> {
         let response = client
             .exchange_code::<OpenIdTokenField>(code, pkce_verifier)
             .await?;
 
-        let token = verify_jwt_with_jwks_endpoint::<Self::User>(
+        let user = validate_id_token(
+            client,
             &response.extra_fields().id_token,
-            &jwks_uri(&client),
-            &client.client_id,
+            IdTokenValidator::for_client(client)
+                .nonce(nonce)
+                .access_token(response.access_token()),
         )
         .await?;
 
-        Ok(Response::from_standard_token_response(
-            &response,
-            token.claims,
-        ))
+        let id_token_subject = IdTokenSubject::new(&user.iss, &user.sub);
+        let user = client.merge_userinfo(response.access_token(), user).await?;
+
+        Ok(Response::from_standard_token_response(&response, user)
+            .with_id_token_subject(Some(id_token_subject)))
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        let response = client
+            .refresh::<OptionalOpenIdTokenField>(refresh_token, scopes)
+            .await?;
+
+        let user = match &response.extra_fields().id_token {
+            Some(id_token) => Some(
+                validate_id_token(
+                    client,
+                    id_token,
+                    IdTokenValidator::for_client(client)
+                        .subject(id_token_subject)
+                        .access_token(response.access_token()),
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
+        let id_token_subject = user
+            .as_ref()
+            .map(|user| IdTokenSubject::new(&user.iss, &user.sub))
+            .or_else(|| id_token_subject.cloned());
+
+        Ok(Response::from_standard_token_response(&response, user)
+            .with_id_token_subject(id_token_subject))
+    }
+}
+
+async fn validate_id_token(
+    client: &SocioClient,
+    id_token: &str,
+    validator: IdTokenValidator,
+) -> error::Result<MicrosoftUser> {
+    let token = validator
+        .validate::<MicrosoftUser>(id_token, &JwksCache::shared(&jwks_uri(client)))
+        .await?;
+
+    if client.issuer.is_none() {
+        validate_multi_tenant_issuer(&token.claims)?;
     }
+
+    Ok(token.claims)
 }
 
 /// Returns the JWKS URI for Microsoft based on the token endpoint.
@@ -76,13 +155,32 @@ pub fn jwks_uri(client: &SocioClient) -> String {
         })
 }
 
+/// Tokens from the multi-tenant endpoints are issued by the user's own tenant, so the
+/// issuer can only be checked against the tenant id within the token.
+fn validate_multi_tenant_issuer(user: &MicrosoftUser) -> error::Result<()> {
+    let tid = user
+        .tid
+        .as_deref()
+        .ok_or_else(|| error::Error::InvalidIdToken("tid claim is missing".to_string()))?;
+
+    let expected = format!("https://login.microsoftonline.com/{tid}/v2.0");
+    if user.iss != expected {
+        return Err(error::Error::IssuerMismatch {
+            expected,
+            found: user.iss.clone(),
+        });
+    }
+
+    Ok(())
+}
+
 impl From<MicrosoftUser> for StandardUser {
     fn from(value: MicrosoftUser) -> Self {
         StandardUser {
             id: value.sub,
             name: Some(value.name),
             email: value.email,
-            picture: None,
+            picture: value.picture,
         }
     }
 }
@@ -94,21 +192,31 @@ pub struct MicrosoftConfig {
     pub redirect_uri: RedirectUrl,
     #[serde(default)]
     pub tenant: TenantType,
+    /// Set to [`ResponseMode::FormPost`] to keep codes out of browser history.
+    #[serde(default)]
+    pub response_mode: Option<ResponseMode>,
 }
 
 impl From<MicrosoftConfig> for SocioClient {
     fn from(value: MicrosoftConfig) -> Self {
-        SocioClient {
-            client_id: value.client_id,
-            client_secret: value.client_secret,
-            redirect_uri: value.redirect_uri,
-            authorize_endpoint: value.tenant.auth_url(),
-            token_endpoint: value.tenant.token_url(),
-            scopes: ["openid", "profile", "email"]
-                .iter()
-                .map(|s| Scope::new(s.to_string()))
-                .collect(),
-        }
+        // The Microsoft identity platform has no revocation endpoint.
+        let mut client = SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_uri,
+            value.tenant.auth_url(),
+            value.tenant.token_url(),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_userinfo_endpoint(
+            "https://graph.microsoft.com/oidc/userinfo"
+                .parse()
+                .expect("Invalid Microsoft userinfo URL"),
+        );
+
+        client.issuer = value.tenant.issuer();
+        client.response_mode = value.response_mode;
+        client
     }
 }
 
@@ -151,6 +259,30 @@ impl TenantType {
         ))
         .expect("Invalid Microsoft token URL")
     }
+
+    /// Returns the issuer of tokens for this tenant.
+    ///
+    /// Multi-tenant endpoints and tenants given by domain name issue tokens under the
+    /// tenant's id, which is not known upfront.
+    pub fn issuer(&self) -> Option<String> {
+        let tenant_id = match self {
+            TenantType::Common => return None,
+            TenantType::Consumers => CONSUMERS_TENANT_ID,
+            TenantType::Tenant(tenant) if is_tenant_id(tenant) => tenant,
+            TenantType::Tenant(_) => return None,
+        };
+
+        Some(format!(
+            "https://login.microsoftonline.com/{tenant_id}/v2.0"
+        ))
+    }
+}
+
+/// Tenant id under which personal Microsoft accounts are issued.
+pub const CONSUMERS_TENANT_ID: &str = "9188040d-6c67-4c5b-b112-36a304b66dad";
+
+fn is_tenant_id(tenant: &str) -> bool {
+    tenant.len() == 36 && tenant.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
 }
 
 #[cfg(test)]
@@ -182,4 +314,17 @@ mod tests {
             tenant.token_url();
         }
     }
+
+    #[test]
+    fn test_issuer() {
+        assert_eq!(TenantType::Common.issuer(), None);
+        assert_eq!(TenantType::Tenant("contoso.com".to_string()).issuer(), None);
+        assert_eq!(
+            TenantType::Tenant("72f988bf-86f1-41af-91ab-2d7cd011db47".to_string()).issuer(),
+            Some(
+                "https://login.microsoftonline.com/72f988bf-86f1-41af-91ab-2d7cd011db47/v2.0"
+                    .to_string()
+            )
+        );
+    }
 }
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, RevocationUrl, Scope, TokenUrl,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{IdTokenSubject, Nonce, Response, SocioClient},
+};
+use url::Url;
+
+use crate::openid::OpenId;
+
+/// An Okta authorization server.
+#[derive(Clone, Debug)]
+pub struct Okta {
+    pub openid: OpenId,
+}
+
+impl Okta {
+    pub fn new(domain: &str, authorization_server_id: Option<&str>) -> error::Result<Self> {
+        Ok(Okta {
+            openid: OpenId::new(endpoint(domain, authorization_server_id, "keys")?),
+        })
+    }
+}
+
+/// Issuer of the authorization server, the org authorization server when no id is given.
+pub fn issuer(domain: &str, authorization_server_id: Option<&str>) -> String {
+    match authorization_server_id {
+        Some(id) => format!("https://{domain}/oauth2/{id}"),
+        None => format!("https://{domain}"),
+    }
+}
+
+fn endpoint(domain: &str, authorization_server_id: Option<&str>, name: &str) -> error::Result<Url> {
+    let base = match authorization_server_id {
+        Some(_) => issuer(domain, authorization_server_id),
+        None => format!("https://{domain}/oauth2"),
+    };
+
+    Url::parse(&format!("{base}/v1/{name}")).map_err(|e| error::Error::Custom(e.into()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OktaUser {
+    pub iss: String,
+    pub sub: String,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    /// Groups of the user, present when the authorization server has a groups claim and
+    /// the `groups` scope is requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+#[async_trait]
+impl SocioProvider for Okta {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Okta {
+    type User = OktaUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        self.openid
+            .exchange_code_as(client, code, pkce_verifier, nonce)
+            .await
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        self.openid
+            .refresh_as(client, refresh_token, scopes, id_token_subject)
+            .await
+    }
+}
+
+impl From<OktaUser> for StandardUser {
+    fn from(value: OktaUser) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.name.or(value.preferred_username),
+            email: value.email,
+            picture: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OktaConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+    /// Domain of the Okta org, such as `acme.okta.com`.
+    pub domain: String,
+    /// Id of a custom authorization server, such as `default`. The org authorization
+    /// server is used when unset.
+    #[serde(default)]
+    pub authorization_server_id: Option<String>,
+    /// Scopes to request, `openid`, `profile` and `email` when unset.
+    #[serde(default)]
+    pub scopes: Option<Vec<Scope>>,
+}
+
+impl TryFrom<OktaConfig> for SocioClient {
+    type Error = error::Error;
+
+    fn try_from(value: OktaConfig) -> Result<Self, Self::Error> {
+        let server_id = value.authorization_server_id.as_deref();
+        let endpoint = |name| endpoint(&value.domain, server_id, name);
+
+        let mut client = SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(endpoint("authorize")?),
+            TokenUrl::from_url(endpoint("token")?),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_issuer(issuer(&value.domain, server_id))
+        .with_revocation_endpoint(RevocationUrl::from_url(endpoint("revoke")?))
+        .with_userinfo_endpoint(endpoint("userinfo")?);
+
+        if let Some(scopes) = value.scopes {
+            client.scopes = scopes;
+        }
+        Ok(client)
+    }
+}
+
+impl TryFrom<OktaConfig> for Socio<Okta> {
+    type Error = error::Error;
+
+    fn try_from(value: OktaConfig) -> Result<Self, Self::Error> {
+        let okta = Okta::new(&value.domain, value.authorization_server_id.as_deref())?;
+        Ok(Socio::new(value.try_into()?, okta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization_server_endpoints() {
+        assert_eq!(
+            issuer("acme.okta.com", Some("default")),
+            "https://acme.okta.com/oauth2/default"
+        );
+        assert_eq!(
+            endpoint("acme.okta.com", Some("default"), "keys")
+                .unwrap()
+                .as_str(),
+            "https://acme.okta.com/oauth2/default/v1/keys"
+        );
+
+        assert_eq!(issuer("acme.okta.com", None), "https://acme.okta.com");
+        assert_eq!(
+            endpoint("acme.okta.com", None, "authorize")
+                .unwrap()
+                .as_str(),
+            "https://acme.okta.com/oauth2/v1/authorize"
+        );
+    }
+}
@@ -1,15 +1,25 @@
-use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use socio::{
-    async_trait, error,
-    jwt::verify_jwt_with_jwks_endpoint,
+    Socio, async_trait,
+    discovery::ProviderMetadata,
+    error,
+    http_client::SharedHttpClient,
+    jwt::{DEFAULT_ALGORITHMS, IdTokenValidator, JwksCache},
+    oauth2::{ClientId, ClientSecret, RedirectUrl, RefreshToken, Scope, TokenResponse},
     providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
-    types::{OpenIdTokenField, Response, SocioClient},
+    types::{
+        IdTokenSubject, Nonce, OpenIdTokenField, OptionalOpenIdTokenField, Response, SocioClient,
+    },
 };
 use url::Url;
 
 #[derive(Clone, Debug)]
 pub struct OpenId {
     pub jwks_url: Url,
+    /// Metadata of the provider, when created through discovery.
+    pub metadata: Option<Arc<ProviderMetadata>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -17,33 +27,207 @@ pub struct OpenIdClaims(serde_json::Value);
 
 impl OpenId {
     pub fn new(jwks_url: Url) -> Self {
-        Self { jwks_url }
+        Self {
+            jwks_url,
+            metadata: None,
+        }
     }
-}
 
-#[async_trait]
-impl SocioProvider for OpenId {
-    async fn exchange_code_standard(
+    pub fn from_metadata(metadata: Arc<ProviderMetadata>) -> Self {
+        Self {
+            jwks_url: metadata.jwks_uri.clone(),
+            metadata: Some(metadata),
+        }
+    }
+
+    /// Discovers the provider from its issuer URL.
+    pub async fn discover(issuer: &str, http_client: &SharedHttpClient) -> error::Result<Self> {
+        Ok(Self::from_metadata(
+            ProviderMetadata::discover(issuer, http_client).await?,
+        ))
+    }
+
+    pub fn jwks(&self) -> Arc<JwksCache> {
+        JwksCache::shared(self.jwks_url.as_str())
+    }
+
+    /// Returns a validator for the client, restricted to the issuer and signing algorithms
+    /// from the discovered metadata when available.
+    pub fn validator(&self, client: &SocioClient) -> IdTokenValidator {
+        let mut validator = IdTokenValidator::for_client(client);
+
+        if let Some(metadata) = &self.metadata {
+            if client.issuer.is_none() {
+                validator = validator.issuer(metadata.issuer.clone());
+            }
+
+            let algorithms = metadata
+                .signing_algorithms()
+                .into_iter()
+                .filter(|alg| DEFAULT_ALGORITHMS.contains(alg))
+                .collect::<Vec<_>>();
+            if !algorithms.is_empty() {
+                validator = validator.algorithms(algorithms);
+            }
+        }
+
+        validator
+    }
+
+    /// Exchanges the code and validates the ID token, completing its claims with the
+    /// UserInfo response when the client has an endpoint for it.
+    ///
+    /// Presets for specific OpenID providers use this with their own claim types.
+    pub async fn exchange_code_as<T: Serialize + DeserializeOwned>(
         &self,
         client: &SocioClient,
         code: socio::oauth2::AuthorizationCode,
         pkce_verifier: socio::oauth2::PkceCodeVerifier,
-    ) -> error::Result<Response<StandardUser>> {
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<T>> {
         let response = client
             .exchange_code::<OpenIdTokenField>(code, pkce_verifier)
             .await?;
 
-        let token = verify_jwt_with_jwks_endpoint::<StandardUser>(
-            &response.extra_fields().id_token,
-            &self.jwks_url.as_str(),
-            &client.client_id,
+        let token = self
+            .validator(client)
+            .nonce(nonce)
+            .access_token(response.access_token())
+            .validate::<T>(&response.extra_fields().id_token, &self.jwks())
+            .await?;
+
+        let id_token_subject = subject_of(&token.claims);
+        let user = client
+            .merge_userinfo(response.access_token(), token.claims)
+            .await?;
+
+        Ok(Response::from_standard_token_response(&response, user)
+            .with_id_token_subject(id_token_subject))
+    }
+
+    /// Refreshes the tokens, validating the ID token when the provider issues a new one.
+    ///
+    /// The new ID token must name the issuer and subject of `id_token_subject`, if given.
+    pub async fn refresh_as<T: Serialize + DeserializeOwned>(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<T>>> {
+        let response = client
+            .refresh::<OptionalOpenIdTokenField>(refresh_token, scopes)
+            .await?;
+
+        let user = match &response.extra_fields().id_token {
+            Some(id_token) => Some(
+                self.validator(client)
+                    .subject(id_token_subject)
+                    .access_token(response.access_token())
+                    .validate::<T>(id_token, &self.jwks())
+                    .await?
+                    .claims,
+            ),
+            None => None,
+        };
+
+        let id_token_subject = match &user {
+            Some(user) => subject_of(user),
+            None => id_token_subject.cloned(),
+        };
+
+        Ok(Response::from_standard_token_response(&response, user)
+            .with_id_token_subject(id_token_subject))
+    }
+}
+
+/// Reads the `iss` and `sub` claims of validated ID token claims.
+fn subject_of<T: Serialize>(claims: &T) -> Option<IdTokenSubject> {
+    let claims = serde_json::to_value(claims).ok()?;
+    Some(IdTokenSubject::new(
+        claims.get("iss")?.as_str()?,
+        claims.get("sub")?.as_str()?,
+    ))
+}
+
+/// Standard claims of an ID token or UserInfo response.
+#[derive(Deserialize)]
+struct StandardClaims {
+    sub: String,
+    name: Option<String>,
+    email: Option<String>,
+    picture: Option<String>,
+}
+
+fn standard_user(claims: serde_json::Value) -> error::Result<StandardUser> {
+    let claims = serde_json::from_value::<StandardClaims>(claims)
+        .map_err(|e| error::Error::InvalidIdToken(e.to_string()))?;
+
+    Ok(StandardUser {
+        id: claims.sub,
+        name: claims.name,
+        email: claims.email,
+        picture: claims.picture,
+    })
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpenIdConfig {
+    pub issuer: String,
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+}
+
+impl OpenIdConfig {
+    /// Discovers the provider and creates a [`Socio`] for it.
+    pub async fn discover(self) -> error::Result<Socio<OpenId>> {
+        self.discover_with(SharedHttpClient::default()).await
+    }
+
+    /// Discovers the provider and creates a [`Socio`] for it, making every request with
+    /// `http_client`.
+    pub async fn discover_with(
+        self,
+        http_client: SharedHttpClient,
+    ) -> error::Result<Socio<OpenId>> {
+        let metadata = ProviderMetadata::discover(&self.issuer, &http_client).await?;
+
+        let client = SocioClient::from_metadata(
+            &metadata,
+            self.client_id,
+            self.client_secret,
+            self.redirect_url,
         )
-        .await?;
+        .with_http_client(http_client);
 
-        Ok(Response::from_standard_token_response(
-            &response,
-            token.claims,
-        ))
+        Ok(Socio::new(client, OpenId::from_metadata(metadata)))
+    }
+}
+
+#[async_trait]
+impl SocioProvider for OpenId {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        self.exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .try_map_user(standard_user)
+    }
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        self.refresh_as::<serde_json::Value>(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .try_map_user(|user| user.map(standard_user).transpose())
     }
 }
 
@@ -56,21 +240,19 @@ impl UserAwareSocioProvider for OpenId {
         client: &SocioClient,
         code: socio::oauth2::AuthorizationCode,
         pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
     ) -> error::Result<Response<Self::User>> {
-        let response = client
-            .exchange_code::<OpenIdTokenField>(code, pkce_verifier)
-            .await?;
-
-        let token = verify_jwt_with_jwks_endpoint::<Self::User>(
-            &response.extra_fields().id_token,
-            &self.jwks_url.as_str(),
-            &client.client_id,
-        )
-        .await?;
-
-        Ok(Response::from_standard_token_response(
-            &response,
-            token.claims,
-        ))
+        self.exchange_code_as(client, code, pkce_verifier, nonce)
+            .await
+    }
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        self.refresh_as(client, refresh_token, scopes, id_token_subject)
+            .await
     }
 }
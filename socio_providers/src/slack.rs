@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    jwt::{IdTokenValidator, JwksCache},
+    oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenResponse, TokenUrl},
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{Nonce, OpenIdTokenField, Response, SocioClient},
+};
+use url_macro::url;
+
+pub const JWKS_URL: &str = "https://slack.com/openid/connect/keys";
+
+/// Sign in with Slack, Slack's OpenID Connect provider.
+#[derive(Clone, Debug)]
+pub struct Slack;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlackUser {
+    pub iss: String,
+    pub aud: String,
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+    pub locale: Option<String>,
+    #[serde(rename = "https://slack.com/user_id")]
+    pub user_id: String,
+    /// Identifier of the workspace the user signed in to.
+    #[serde(rename = "https://slack.com/team_id")]
+    pub team_id: String,
+    #[serde(rename = "https://slack.com/team_name")]
+    pub team_name: Option<String>,
+    #[serde(rename = "https://slack.com/team_domain")]
+    pub team_domain: Option<String>,
+}
+
+#[async_trait]
+impl SocioProvider for Slack {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Slack {
+    type User = SlackUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        let response = client
+            .exchange_code::<OpenIdTokenField>(code, pkce_verifier)
+            .await?;
+
+        // The ID token already carries every claim of the UserInfo response.
+        let token = IdTokenValidator::for_client(client)
+            .nonce(nonce)
+            .access_token(response.access_token())
+            .validate::<SlackUser>(
+                &response.extra_fields().id_token,
+                &JwksCache::shared(JWKS_URL),
+            )
+            .await?;
+
+        Ok(Response::from_standard_token_response(
+            &response,
+            token.claims,
+        ))
+    }
+}
+
+impl From<SlackUser> for StandardUser {
+    fn from(value: SlackUser) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.name,
+            email: value.email,
+            picture: value.picture,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SlackConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+}
+
+impl From<SlackConfig> for SocioClient {
+    fn from(value: SlackConfig) -> Self {
+        let auth_url = url!("https://slack.com/openid/connect/authorize");
+        let token_url = url!("https://slack.com/api/openid.connect.token");
+
+        SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(auth_url),
+            TokenUrl::from_url(token_url),
+        )
+        .with_scopes(["openid", "profile", "email"])
+        .with_issuer("https://slack.com")
+    }
+}
+
+impl From<SlackConfig> for Socio<Slack> {
+    fn from(value: SlackConfig) -> Self {
+        Socio::new(value.into(), Slack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_team_claims() {
+        let user = serde_json::from_str::<SlackUser>(
+            r#"{
+                "iss": "https://slack.com",
+                "aud": "25259531569.1115258246291",
+                "sub": "U0R7JM",
+                "email": "krane@slack-corp.com",
+                "email_verified": true,
+                "https://slack.com/user_id": "U0R7JM",
+                "https://slack.com/team_id": "T0R7GR",
+                "https://slack.com/team_name": "kraneflannel"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(user.team_id, "T0R7GR");
+        assert_eq!(user.team_name.as_deref(), Some("kraneflannel"));
+        assert_eq!(user.team_domain, None);
+    }
+}
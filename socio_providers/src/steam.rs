@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use socio::{
+    error,
+    http_client::{SharedHttpClient, parse_json},
+    integrations::{AuthorizationError, AuthorizationErrorKind},
+    providers::StandardUser,
+};
+use url::Url;
+
+/// Steam OpenID 2.0 provider endpoint.
+pub const OPENID_URL: &str = "https://steamcommunity.com/openid/login";
+pub const PLAYER_SUMMARIES_URL: &str =
+    "https://api.steampowered.com/ISteamUser/GetPlayerSummaries/v2/";
+
+const OPENID_NS: &str = "http://specs.openid.net/auth/2.0";
+const IDENTIFIER_SELECT: &str = "http://specs.openid.net/auth/2.0/identifier_select";
+const CLAIMED_ID_PREFIX: &str = "https://steamcommunity.com/openid/id/";
+
+/// Fields the assertion signature must cover for it to be trusted.
+const SIGNED_FIELDS: [&str; 6] = [
+    "op_endpoint",
+    "claimed_id",
+    "identity",
+    "return_to",
+    "response_nonce",
+    "assoc_handle",
+];
+
+/// Sign in through Steam.
+///
+/// Steam is an OpenID 2.0 provider rather than an OAuth 2.0 one, so it does not go through
+/// [`socio::Socio`]. Redirect the user to [`Steam::authorize_url`], then pass the query
+/// parameters of the request to `return_to` to [`Steam::verify`] or [`Steam::user`].
+#[derive(Clone)]
+pub struct Steam {
+    /// URL Steam redirects the user back to.
+    pub return_to: Url,
+    /// Realm the user is asked to trust, the origin of `return_to` by default.
+    pub realm: String,
+    /// Steam Web API key, used to fetch the player summary.
+    pub api_key: Option<String>,
+    pub http_client: SharedHttpClient,
+}
+
+impl std::fmt::Debug for Steam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Steam")
+            .field("return_to", &self.return_to)
+            .field("realm", &self.realm)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A 64 bit Steam ID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SteamId(pub u64);
+
+impl std::fmt::Display for SteamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SteamUser {
+    pub steam_id: SteamId,
+    /// Public profile of the player, when a Web API key is configured.
+    pub player: Option<SteamPlayer>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SteamPlayer {
+    pub steamid: String,
+    pub personaname: String,
+    pub profileurl: String,
+    pub avatarfull: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PlayerSummaries {
+    response: PlayerSummariesResponse,
+}
+
+#[derive(Deserialize)]
+struct PlayerSummariesResponse {
+    players: Vec<SteamPlayer>,
+}
+
+impl Steam {
+    pub fn new(return_to: Url) -> Self {
+        let realm = format!("{}/", return_to.origin().ascii_serialization());
+
+        Steam {
+            return_to,
+            realm,
+            api_key: None,
+            http_client: SharedHttpClient::default(),
+        }
+    }
+
+    pub fn with_http_client(mut self, http_client: SharedHttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// URL of the `checkid_setup` request to redirect the user to.
+    pub fn authorize_url(&self) -> Url {
+        let mut url = Url::parse(OPENID_URL).expect("Invalid Steam OpenID URL");
+        url.query_pairs_mut()
+            .append_pair("openid.ns", OPENID_NS)
+            .append_pair("openid.mode", "checkid_setup")
+            .append_pair("openid.return_to", self.return_to.as_str())
+            .append_pair("openid.realm", &self.realm)
+            .append_pair("openid.identity", IDENTIFIER_SELECT)
+            .append_pair("openid.claimed_id", IDENTIFIER_SELECT);
+        url
+    }
+
+    /// Verifies the assertion Steam redirected the user back with and returns the Steam ID
+    /// of the user.
+    ///
+    /// The signature is checked by asking Steam to verify the assertion, which also
+    /// prevents it from being replayed.
+    pub async fn verify(&self, params: &HashMap<String, String>) -> error::Result<SteamId> {
+        let steam_id = self.check_assertion(params)?;
+
+        let mut request = params
+            .iter()
+            .filter(|(key, _)| key.starts_with("openid.") && *key != "openid.mode")
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        request.push(("openid.mode", "check_authentication"));
+
+        let response = self.http_client.post_form(OPENID_URL, &request).await?;
+        let body = String::from_utf8_lossy(response.body());
+
+        if key_value(&body, "is_valid") != Some("true") {
+            return Err(invalid("Steam rejected the assertion"));
+        }
+
+        Ok(steam_id)
+    }
+
+    /// Verifies the assertion and fetches the player summary when a Web API key is
+    /// configured.
+    pub async fn user(&self, params: &HashMap<String, String>) -> error::Result<SteamUser> {
+        let steam_id = self.verify(params).await?;
+
+        let player = match &self.api_key {
+            Some(api_key) => self.player_summary(api_key, steam_id).await?,
+            None => None,
+        };
+
+        Ok(SteamUser { steam_id, player })
+    }
+
+    /// Fetches the public profile of a player through the Steam Web API.
+    pub async fn player_summary(
+        &self,
+        api_key: &str,
+        steam_id: SteamId,
+    ) -> error::Result<Option<SteamPlayer>> {
+        let mut url = Url::parse(PLAYER_SUMMARIES_URL).expect("Invalid Steam Web API URL");
+        url.query_pairs_mut()
+            .append_pair("key", api_key)
+            .append_pair("steamids", &steam_id.to_string());
+
+        let response = self.http_client.get(url.as_str(), None).await?;
+        let summaries = parse_json::<PlayerSummaries>(&response)?;
+
+        Ok(summaries.response.players.into_iter().next())
+    }
+
+    /// Checks everything about the assertion that can be checked locally.
+    fn check_assertion(&self, params: &HashMap<String, String>) -> error::Result<SteamId> {
+        let param = |name: &str| params.get(&format!("openid.{name}")).map(String::as_str);
+
+        match param("mode") {
+            Some("id_res") => {}
+            Some("cancel") => {
+                return Err(error::Error::AuthorizationError(AuthorizationError {
+                    kind: AuthorizationErrorKind::AccessDenied,
+                    description: Some("The user cancelled the login".to_string()),
+                    uri: None,
+                }));
+            }
+            _ => return Err(invalid("unexpected openid.mode")),
+        }
+
+        if param("ns") != Some(OPENID_NS) {
+            return Err(invalid("unexpected openid.ns"));
+        }
+
+        if param("op_endpoint") != Some(OPENID_URL) {
+            return Err(invalid("unexpected openid.op_endpoint"));
+        }
+
+        if param("return_to") != Some(self.return_to.as_str()) {
+            return Err(invalid("openid.return_to does not match"));
+        }
+
+        let signed = param("signed")
+            .unwrap_or_default()
+            .split(',')
+            .collect::<Vec<_>>();
+        if let Some(field) = SIGNED_FIELDS.iter().find(|field| !signed.contains(field)) {
+            return Err(invalid(format!("openid.{field} is not signed")));
+        }
+
+        let claimed_id = param("claimed_id").unwrap_or_default();
+        if param("identity") != Some(claimed_id) {
+            return Err(invalid("openid.identity does not match openid.claimed_id"));
+        }
+
+        claimed_id
+            .strip_prefix(CLAIMED_ID_PREFIX)
+            .and_then(|id| id.parse().ok())
+            .map(SteamId)
+            .ok_or_else(|| invalid("openid.claimed_id is not a Steam ID"))
+    }
+}
+
+/// Reads a value from an OpenID key-value form encoded response.
+fn key_value<'a>(body: &'a str, key: &str) -> Option<&'a str> {
+    body.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| value.trim_end())
+}
+
+fn invalid(reason: impl Into<String>) -> error::Error {
+    error::Error::InvalidCallback(reason.into())
+}
+
+impl From<SteamUser> for StandardUser {
+    fn from(value: SteamUser) -> Self {
+        let player = value.player;
+
+        StandardUser {
+            id: value.steam_id.to_string(),
+            name: player.as_ref().map(|player| player.personaname.clone()),
+            email: None,
+            picture: player.and_then(|player| player.avatarfull),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SteamConfig {
+    pub return_to: Url,
+    #[serde(default)]
+    pub realm: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+impl From<SteamConfig> for Steam {
+    fn from(value: SteamConfig) -> Self {
+        let mut steam = Steam::new(value.return_to);
+        if let Some(realm) = value.realm {
+            steam.realm = realm;
+        }
+        steam.api_key = value.api_key;
+        steam
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url_macro::url;
+
+    use super::*;
+
+    fn assertion() -> HashMap<String, String> {
+        [
+            ("openid.ns", OPENID_NS),
+            ("openid.mode", "id_res"),
+            ("openid.op_endpoint", OPENID_URL),
+            (
+                "openid.claimed_id",
+                "https://steamcommunity.com/openid/id/76561197960287930",
+            ),
+            (
+                "openid.identity",
+                "https://steamcommunity.com/openid/id/76561197960287930",
+            ),
+            ("openid.return_to", "https://example.com/steam/callback"),
+            ("openid.response_nonce", "2025-01-01T00:00:00ZaBcD"),
+            ("openid.assoc_handle", "1234567890"),
+            (
+                "openid.signed",
+                "signed,op_endpoint,claimed_id,identity,return_to,response_nonce,assoc_handle",
+            ),
+            ("openid.sig", "c2lnbmF0dXJl"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_authorize_url() {
+        let steam = Steam::new(url!("https://example.com/steam/callback"));
+        let url = steam.authorize_url();
+        let params = url.query_pairs().collect::<HashMap<_, _>>();
+
+        assert_eq!(params["openid.mode"], "checkid_setup");
+        assert_eq!(params["openid.realm"], "https://example.com/");
+        assert_eq!(params["openid.claimed_id"], IDENTIFIER_SELECT);
+    }
+
+    #[test]
+    fn test_check_assertion() {
+        let steam = Steam::new(url!("https://example.com/steam/callback"));
+        assert_eq!(
+            steam.check_assertion(&assertion()).unwrap(),
+            SteamId(76561197960287930)
+        );
+
+        let mut params = assertion();
+        params.insert(
+            "openid.return_to".to_string(),
+            "https://evil.example/steam/callback".to_string(),
+        );
+        assert!(matches!(
+            steam.check_assertion(&params),
+            Err(error::Error::InvalidCallback(_))
+        ));
+
+        let mut params = assertion();
+        params.insert(
+            "openid.signed".to_string(),
+            "signed,op_endpoint,identity,return_to".to_string(),
+        );
+        assert!(steam.check_assertion(&params).is_err());
+
+        let mut params = assertion();
+        params.insert(
+            "openid.claimed_id".to_string(),
+            "https://evil.example/openid/id/76561197960287930".to_string(),
+        );
+        assert!(steam.check_assertion(&params).is_err());
+    }
+
+    #[test]
+    fn test_key_value() {
+        let body = "ns:http://specs.openid.net/auth/2.0\nis_valid:true\n";
+        assert_eq!(key_value(body, "is_valid"), Some("true"));
+        assert_eq!(key_value("is_valid:false\n", "is_valid"), Some("false"));
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use socio::{error, providers::StandardUser};
+
+/// Telegram Login Widget.
+///
+/// Telegram does not use OAuth, the widget redirects the user with query parameters signed
+/// with the bot token. Extract them as a [`TelegramCallback`], for example with
+/// `ExtractCallback<TelegramCallback>`, and pass them to [`Telegram::verify`].
+#[derive(Clone)]
+pub struct Telegram {
+    bot_token: String,
+    /// How old the authentication may be, one day by default.
+    pub max_age: Duration,
+    /// Clock skew tolerated for an `auth_date` in the future, one minute by default.
+    pub clock_skew: Duration,
+}
+
+impl std::fmt::Debug for Telegram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Telegram")
+            .field("max_age", &self.max_age)
+            .field("clock_skew", &self.clock_skew)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parameters the Login Widget redirects the user with.
+///
+/// They are kept as received since every one of them, including those this crate does not
+/// know about, is covered by the hash.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TelegramCallback(pub BTreeMap<String, String>);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TelegramUser {
+    pub id: i64,
+    pub first_name: String,
+    pub last_name: Option<String>,
+    pub username: Option<String>,
+    pub photo_url: Option<String>,
+    /// Unix time at which the user authenticated.
+    pub auth_date: u64,
+}
+
+impl Telegram {
+    pub fn new(bot_token: impl Into<String>) -> Self {
+        Telegram {
+            bot_token: bot_token.into(),
+            max_age: Duration::from_secs(24 * 60 * 60),
+            clock_skew: Duration::from_secs(60),
+        }
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn clock_skew(mut self, clock_skew: Duration) -> Self {
+        self.clock_skew = clock_skew;
+        self
+    }
+
+    /// Checks the hash and the freshness of the callback, and returns the user.
+    pub fn verify(&self, callback: &TelegramCallback) -> error::Result<TelegramUser> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| error::Error::Custom(e.into()))?;
+        self.verify_at(callback, now.as_secs())
+    }
+
+    fn verify_at(&self, callback: &TelegramCallback, now: u64) -> error::Result<TelegramUser> {
+        let params = &callback.0;
+
+        let hash = params
+            .get("hash")
+            .and_then(|hash| decode_hex(hash))
+            .ok_or_else(|| invalid("missing or malformed hash"))?;
+
+        // The data check string is every other parameter, sorted by name, as `name=value`
+        // lines.
+        let data_check_string = params
+            .iter()
+            .filter(|(name, _)| *name != "hash")
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&Sha256::digest(self.bot_token.as_bytes()))
+            .expect("HMAC accepts keys of any size");
+        mac.update(data_check_string.as_bytes());
+        mac.verify_slice(&hash)
+            .map_err(|_| invalid("hash does not match"))?;
+
+        let user = TelegramUser {
+            id: parse(params, "id")?,
+            first_name: params
+                .get("first_name")
+                .cloned()
+                .ok_or_else(|| invalid("missing first_name"))?,
+            last_name: params.get("last_name").cloned(),
+            username: params.get("username").cloned(),
+            photo_url: params.get("photo_url").cloned(),
+            auth_date: parse(params, "auth_date")?,
+        };
+
+        if now.saturating_sub(user.auth_date) > self.max_age.as_secs() {
+            return Err(invalid("authentication is older than max_age"));
+        }
+
+        if user.auth_date > now + self.clock_skew.as_secs() {
+            return Err(invalid("authentication is in the future"));
+        }
+
+        Ok(user)
+    }
+}
+
+fn parse<T: std::str::FromStr>(params: &BTreeMap<String, String>, name: &str) -> error::Result<T> {
+    params
+        .get(name)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| invalid(format!("missing or malformed {name}")))
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn invalid(reason: impl Into<String>) -> error::Error {
+    error::Error::InvalidCallback(reason.into())
+}
+
+impl From<TelegramUser> for StandardUser {
+    fn from(value: TelegramUser) -> Self {
+        let name = match value.last_name {
+            Some(last_name) => format!("{} {last_name}", value.first_name),
+            None => value.first_name,
+        };
+
+        StandardUser {
+            id: value.id.to_string(),
+            name: Some(name),
+            email: None,
+            picture: value.photo_url,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+}
+
+impl From<TelegramConfig> for Telegram {
+    fn from(value: TelegramConfig) -> Self {
+        Telegram::new(value.bot_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTH_DATE: u64 = 1700000000;
+
+    fn callback(hash: &str) -> TelegramCallback {
+        TelegramCallback(
+            [
+                ("id", "42"),
+                ("first_name", "John"),
+                ("last_name", "Doe"),
+                ("username", "jdoe"),
+                ("photo_url", "https://t.me/i/userpic/320/jdoe.jpg"),
+                ("auth_date", "1700000000"),
+                ("hash", hash),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let telegram = Telegram::new("123456:ABC-DEF");
+        let valid = callback("ce3a050528cbe2e074d17b5429bb191a5e88db91f3b102bc85c50ae172ef626b");
+
+        let user = telegram.verify_at(&valid, AUTH_DATE + 60).unwrap();
+        assert_eq!(user.id, 42);
+        assert_eq!(StandardUser::from(user).name.as_deref(), Some("John Doe"));
+
+        assert!(matches!(
+            telegram.verify_at(&valid, AUTH_DATE + 2 * 24 * 60 * 60),
+            Err(error::Error::InvalidCallback(_))
+        ));
+        assert!(telegram.verify_at(&valid, AUTH_DATE - 30).is_ok());
+        assert!(matches!(
+            telegram.verify_at(&valid, AUTH_DATE - 2 * 60),
+            Err(error::Error::InvalidCallback(_))
+        ));
+
+        let mut tampered = valid.clone();
+        tampered.0.insert("id".to_string(), "43".to_string());
+        assert!(telegram.verify_at(&tampered, AUTH_DATE).is_err());
+
+        assert!(
+            Telegram::new("654321:XYZ")
+                .verify_at(&valid, AUTH_DATE)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    jwt::{IdTokenValidator, JwksCache},
+    oauth2::{
+        AuthType, AuthUrl, ClientId, ClientSecret, RedirectUrl, RefreshToken, RevocationUrl, Scope,
+        TokenResponse, TokenUrl,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{
+        IdTokenSubject, Nonce, OpenIdTokenField, OptionalOpenIdTokenField, Response, SocioClient,
+    },
+};
+use url_macro::url;
+
+pub const JWKS_URL: &str = "https://id.twitch.tv/oauth2/keys";
+
+/// Claims Twitch only includes in the ID token when they are requested. Requesting them
+/// here makes a call to the UserInfo endpoint unnecessary.
+const CLAIMS: &str =
+    r#"{"id_token":{"email":null,"email_verified":null,"picture":null,"preferred_username":null}}"#;
+
+#[derive(Clone, Debug)]
+pub struct Twitch;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwitchUser {
+    pub iss: String,
+    pub aud: String,
+    pub sub: String,
+    /// Display name of the user.
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub picture: Option<String>,
+}
+
+#[async_trait]
+impl SocioProvider for Twitch {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+
+    async fn refresh_standard(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<StandardUser>>> {
+        Ok(self
+            .refresh_for_user(client, refresh_token, scopes, id_token_subject)
+            .await?
+            .standardize_optional())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for Twitch {
+    type User = TwitchUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        let response = client
+            .exchange_code::<OpenIdTokenField>(code, pkce_verifier)
+            .await?;
+
+        let token = IdTokenValidator::for_client(client)
+            .nonce(nonce)
+            .access_token(response.access_token())
+            .validate::<TwitchUser>(
+                &response.extra_fields().id_token,
+                &JwksCache::shared(JWKS_URL),
+            )
+            .await?;
+
+        let id_token_subject = IdTokenSubject::new(&token.claims.iss, &token.claims.sub);
+
+        Ok(
+            Response::from_standard_token_response(&response, token.claims)
+                .with_id_token_subject(Some(id_token_subject)),
+        )
+    }
+
+    async fn refresh_for_user(
+        &self,
+        client: &SocioClient,
+        refresh_token: &RefreshToken,
+        scopes: Option<Vec<Scope>>,
+        id_token_subject: Option<&IdTokenSubject>,
+    ) -> error::Result<Response<Option<Self::User>>> {
+        let response = client
+            .refresh::<OptionalOpenIdTokenField>(refresh_token, scopes)
+            .await?;
+
+        let user = match &response.extra_fields().id_token {
+            Some(id_token) => Some(
+                IdTokenValidator::for_client(client)
+                    .subject(id_token_subject)
+                    .access_token(response.access_token())
+                    .validate::<TwitchUser>(id_token, &JwksCache::shared(JWKS_URL))
+                    .await?
+                    .claims,
+            ),
+            None => None,
+        };
+
+        let id_token_subject = user
+            .as_ref()
+            .map(|user| IdTokenSubject::new(&user.iss, &user.sub))
+            .or_else(|| id_token_subject.cloned());
+
+        Ok(Response::from_standard_token_response(&response, user)
+            .with_id_token_subject(id_token_subject))
+    }
+}
+
+impl From<TwitchUser> for StandardUser {
+    fn from(value: TwitchUser) -> Self {
+        StandardUser {
+            id: value.sub,
+            name: value.preferred_username,
+            email: value.email,
+            picture: value.picture,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TwitchConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+}
+
+impl From<TwitchConfig> for SocioClient {
+    fn from(value: TwitchConfig) -> Self {
+        let auth_url = url!("https://id.twitch.tv/oauth2/authorize");
+        let token_url = url!("https://id.twitch.tv/oauth2/token");
+
+        SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(auth_url),
+            TokenUrl::from_url(token_url),
+        )
+        .with_scopes(["openid", "user:read:email"])
+        .with_issuer("https://id.twitch.tv/oauth2")
+        .with_revocation_endpoint(RevocationUrl::from_url(url!(
+            "https://id.twitch.tv/oauth2/revoke"
+        )))
+        // Twitch does not accept client credentials in the authorization header.
+        .with_auth_type(AuthType::RequestBody)
+        .with_extra_param("claims", CLAIMS)
+    }
+}
+
+impl From<TwitchConfig> for Socio<Twitch> {
+    fn from(value: TwitchConfig) -> Self {
+        Socio::new(value.into(), Twitch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> SocioClient {
+        SocioClient::from(TwitchConfig {
+            client_id: ClientId::new("app".to_string()),
+            client_secret: ClientSecret::new("secret".to_string()),
+            redirect_url: RedirectUrl::new("https://app.example.com/callback".to_string()).unwrap(),
+        })
+    }
+
+    #[test]
+    fn test_claims_param() {
+        let request = client().authorize(None).unwrap();
+
+        let claims = request
+            .url
+            .query_pairs()
+            .find(|(name, _)| name == "claims")
+            .map(|(_, value)| value.into_owned());
+        assert_eq!(claims.as_deref(), Some(CLAIMS));
+    }
+
+    #[test]
+    fn test_standard_user() {
+        let user = serde_json::from_str::<TwitchUser>(
+            r#"{
+                "iss": "https://id.twitch.tv/oauth2",
+                "aud": "app",
+                "sub": "713936733",
+                "preferred_username": "twitchdev",
+                "email": "dev@example.com",
+                "email_verified": true,
+                "picture": "https://static-cdn.jtvnw.net/user-default-pictures/profile.png"
+            }"#,
+        )
+        .unwrap();
+
+        let user = StandardUser::from(user);
+        assert_eq!(user.id, "713936733");
+        assert_eq!(user.name.as_deref(), Some("twitchdev"));
+        assert_eq!(user.email.as_deref(), Some("dev@example.com"));
+        assert_eq!(
+            user.picture.as_deref(),
+            Some("https://static-cdn.jtvnw.net/user-default-pictures/profile.png")
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use socio::{
+    Socio, async_trait, error,
+    oauth2::{
+        AuthUrl, ClientId, ClientSecret, EmptyExtraTokenFields, RedirectUrl, RevocationUrl,
+        TokenResponse, TokenUrl,
+    },
+    providers::{SocioProvider, StandardUser, UserAwareSocioProvider},
+    types::{Nonce, Response, SocioClient},
+};
+use url_macro::url;
+
+pub const USER_URL: &str =
+    "https://api.x.com/2/users/me?user.fields=profile_image_url,verified,description";
+
+/// X, formerly Twitter, through OAuth 2.0 since it issues no ID token.
+#[derive(Clone, Debug)]
+pub struct X;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct XUser {
+    pub id: String,
+    pub name: String,
+    pub username: String,
+    pub profile_image_url: Option<String>,
+    pub verified: Option<bool>,
+    pub description: Option<String>,
+}
+
+/// Envelope of the X API v2 responses.
+#[derive(Deserialize)]
+struct Data<T> {
+    data: T,
+}
+
+#[async_trait]
+impl SocioProvider for X {
+    async fn exchange_code_standard(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        nonce: Option<&Nonce>,
+    ) -> error::Result<Response<StandardUser>> {
+        Ok(self
+            .exchange_code_for_user(client, code, pkce_verifier, nonce)
+            .await?
+            .standardize())
+    }
+}
+
+#[async_trait]
+impl UserAwareSocioProvider for X {
+    type User = XUser;
+
+    async fn exchange_code_for_user(
+        &self,
+        client: &SocioClient,
+        code: socio::oauth2::AuthorizationCode,
+        pkce_verifier: socio::oauth2::PkceCodeVerifier,
+        _nonce: Option<&Nonce>,
+    ) -> error::Result<Response<Self::User>> {
+        let response = client
+            .exchange_code::<EmptyExtraTokenFields>(code, pkce_verifier)
+            .await?;
+
+        let user = client
+            .http_client
+            .get_json::<Data<XUser>>(USER_URL, response.access_token(), &[])
+            .await?
+            .data;
+
+        Ok(Response::from_standard_token_response(&response, user))
+    }
+}
+
+impl From<XUser> for StandardUser {
+    fn from(value: XUser) -> Self {
+        StandardUser {
+            id: value.id,
+            name: Some(value.name),
+            // X does not share the email address through this API.
+            email: None,
+            picture: value.profile_image_url,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct XConfig {
+    pub client_id: ClientId,
+    pub client_secret: ClientSecret,
+    pub redirect_url: RedirectUrl,
+}
+
+impl From<XConfig> for SocioClient {
+    fn from(value: XConfig) -> Self {
+        let auth_url = url!("https://x.com/i/oauth2/authorize");
+        let token_url = url!("https://api.x.com/2/oauth2/token");
+
+        // Confidential clients must authenticate with HTTP basic authentication.
+        SocioClient::new(
+            value.client_id,
+            value.client_secret,
+            value.redirect_url,
+            AuthUrl::from_url(auth_url),
+            TokenUrl::from_url(token_url),
+        )
+        .with_scopes(["tweet.read", "users.read"])
+        .with_revocation_endpoint(RevocationUrl::from_url(url!(
+            "https://api.x.com/2/oauth2/revoke"
+        )))
+    }
+}
+
+impl From<XConfig> for Socio<X> {
+    fn from(value: XConfig) -> Self {
+        Socio::new(value.into(), X)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_user() {
+        let user = serde_json::from_str::<Data<XUser>>(
+            r#"{
+                "data": {
+                    "id": "2244994945",
+                    "name": "X Dev",
+                    "username": "XDevelopers",
+                    "profile_image_url": "https://pbs.twimg.com/profile_images/1/normal.jpg"
+                }
+            }"#,
+        )
+        .unwrap()
+        .data;
+
+        let user = StandardUser::from(user);
+        assert_eq!(user.id, "2244994945");
+        assert_eq!(user.name.as_deref(), Some("X Dev"));
+        assert_eq!(user.email, None);
+    }
+}